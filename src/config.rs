@@ -0,0 +1,103 @@
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use anyhow::anyhow;
+use clap::{CommandFactory, FromArgMatches};
+use colored::Colorize;
+use toml::{Table, Value};
+
+use crate::Cli;
+
+/// The config file that gets picked up when `--config` isn't passed
+const DEFAULT_CONFIG: &str = "./chess-bench.toml";
+
+/// Parse the command line arguments, using the values in the config file as
+/// defaults. Anything passed on the command line takes precedence over the
+/// config file.
+///
+/// The keys in the config file are the long flags (e.g., `max-nodes`), or
+/// `engine` for the engine binary. Unknown keys are skipped with a warning.
+pub fn parse_args() -> anyhow::Result<Cli> {
+    let args = std::env::args_os().collect::<Vec<_>>();
+    let mut command = Cli::command();
+
+    if let Some((path, config)) = load_config(&args)? {
+        for (key, value) in config {
+            // Match on the id first, so `engine` picks the positional engine
+            // rather than `--engine`
+            let args = || command.get_arguments()
+                .filter(|arg| !["help", "version", "config"].contains(&arg.get_id().as_str()));
+
+            let id = args()
+                .find(|arg| arg.get_id() == key.replace('-', "_").as_str())
+                .or_else(|| args().find(|arg| arg.get_long() == Some(&key)))
+                .map(|arg| arg.get_id().clone());
+
+            let Some(id) = id else {
+                eprintln!("{} ignoring unknown key '{key}' in {}", "Warning:".yellow(), path.display());
+                continue;
+            };
+
+            let Some(values) = arg_values(&value) else {
+                eprintln!("{} ignoring unsupported value for '{key}' in {}", "Warning:".yellow(), path.display());
+                continue;
+            };
+
+            command = command.mut_arg(id, |arg| arg
+                .default_values(values)
+                .required(false)
+                .required_unless_present(clap::builder::Resettable::Reset)
+            );
+        }
+    }
+
+    let matches = command.get_matches_from(args);
+
+    Ok(Cli::from_arg_matches(&matches).unwrap_or_else(|err| err.exit()))
+}
+
+/// Read the config file passed with `--config`, or the default one if there
+/// is one. Returns `None` when there's no config file to read.
+fn load_config(args: &[OsString]) -> anyhow::Result<Option<(PathBuf, Table)>> {
+    // The config file provides the defaults for the real parse, so pick out
+    // the `--config` flag first, and leave any errors for the real parse
+    let explicit = Cli::command()
+        .ignore_errors(true)
+        .try_get_matches_from(args)
+        .ok()
+        .and_then(|matches| matches.get_one::<PathBuf>("config").cloned());
+
+    let path = match explicit {
+        Some(path) => path,
+        None if Path::new(DEFAULT_CONFIG).exists() => PathBuf::from(DEFAULT_CONFIG),
+        None => return Ok(None),
+    };
+
+    let contents = std::fs::read_to_string(&path)
+        .map_err(|err| anyhow!("Failed to read config {}: {err}", path.display()))?;
+
+    let config = contents.parse::<Table>()
+        .map_err(|err| anyhow!("Failed to parse config {}: {err}", path.display()))?;
+
+    Ok(Some((path, config)))
+}
+
+/// The values of a config entry, as they would be passed on the command line.
+/// Arrays are used for flags that can be passed multiple times.
+fn arg_values(value: &Value) -> Option<Vec<String>> {
+    match value {
+        Value::Array(values) => values.iter().map(arg_value).collect(),
+        value => arg_value(value).map(|value| vec![value]),
+    }
+}
+
+/// A single config value, as it would be passed on the command line
+fn arg_value(value: &Value) -> Option<String> {
+    match value {
+        Value::String(value) => Some(value.clone()),
+        Value::Integer(value) => Some(value.to_string()),
+        Value::Float(value) => Some(value.to_string()),
+        Value::Boolean(value) => Some(value.to_string()),
+        _ => None,
+    }
+}
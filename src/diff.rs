@@ -1,13 +1,18 @@
 use std::fmt::Display;
 use std::iter::Sum;
+use clap::ValueEnum;
 use std::ops::Add;
 use std::ops::Div;
+use std::str::FromStr;
+use std::sync::OnceLock;
+use anyhow::anyhow;
 use colored::Color;
 use colored::Colorize;
 use serde::Deserialize;
 use serde::Serialize;
 use crate::fields::Extract;
 use crate::fields::Fields;
+use crate::fields::SortKey;
 use crate::search_result::SearchResult;
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -15,27 +20,96 @@ use crate::search_result::SearchResult;
 /// Diff
 ///
 ////////////////////////////////////////////////////////////////////////////////
-#[derive(Default)]
+#[derive(Default, Clone, Serialize)]
 pub struct Diff {
     pub position: String,
+
+    /// The label of the position, if it has one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+
     pub depth: usize,
     pub nodes: NodeDiff,
     pub time: TimeDiff,
     pub nps: NpsDiff,
     pub score: ScoreDiff,
     pub branching_factor: BFactorDiff,
+    pub ebf: BFactorDiff,
+    pub reached_depth: ReachedDepthDiff,
+    pub seldepth: SeldepthDiff,
+    pub hashfull: HashfullDiff,
+    pub tbhits: TbHitsDiff,
+    pub time_to_depth: TimeToDepthDiff,
+    pub best_move: BestMoveDiff,
+    pub pv: PvDiff,
 }
 
 impl Diff {
     pub fn new(first: &SearchResult, second: &SearchResult) -> Self {
         Self {
             position: first.position.clone(),
+            id: first.id.clone(),
             depth: first.depth,
             nodes: NodeDiff::new(first.nodes, second.nodes),
             time: TimeDiff::new(first.time, second.time),
             nps: NpsDiff::new(first.nps, second.nps),
             score: ScoreDiff::new(first.score, second.score),
-            branching_factor: BFactorDiff::new(first.branching_factor, second.branching_factor)
+            branching_factor: BFactorDiff::new(first.branching_factor, second.branching_factor),
+            ebf: BFactorDiff::new(first.ebf, second.ebf),
+            reached_depth: ReachedDepthDiff::new(first.reached_depth, second.reached_depth),
+            seldepth: SeldepthDiff::new(first.seldepth, second.seldepth),
+            hashfull: HashfullDiff::new(first.hashfull, second.hashfull),
+            tbhits: TbHitsDiff::new(first.tbhits, second.tbhits),
+            time_to_depth: TimeToDepthDiff::new(first.time_to_depth, second.time_to_depth),
+            best_move: BestMoveDiff::new(&first.best_move, &second.best_move),
+            pv: PvDiff::new(&first.pv, &second.pv),
+        }
+    }
+}
+
+impl Diff {
+    /// How much worse the given metric got, as a fraction. Positive values are
+    /// regressions, negative values are improvements. Returns `None` when the
+    /// relative change isn't available.
+    pub fn regression(&self, metric: GateMetric) -> Option<f32> {
+        match worse_is(SortKey::from(metric)) {
+            Worse::Higher => self.relative(metric),
+            Worse::Lower => self.relative(metric).map(|relative| -relative),
+        }
+    }
+
+    /// The relative change in the given metric, as a fraction
+    pub fn relative(&self, metric: GateMetric) -> Option<f32> {
+        match metric {
+            GateMetric::Nps => self.nps.relative,
+            GateMetric::Nodes => self.nodes.relative,
+            GateMetric::Time => self.time.relative,
+        }
+    }
+}
+
+impl Diff {
+    /// The value of the given metric in the new run, or the magnitude of its 
+    /// relative change, for sorting rows
+    pub fn sort_value(&self, key: SortKey, relative: bool) -> f32 {
+        if relative {
+            let relative = match key {
+                SortKey::Nodes => self.nodes.relative,
+                SortKey::Time => self.time.relative,
+                SortKey::Nps => self.nps.relative,
+                SortKey::Branching => self.branching_factor.relative,
+                SortKey::Score => self.score.relative,
+            };
+
+            return relative.unwrap_or_default().abs();
+        }
+
+        match key {
+            SortKey::Nodes => self.nodes.second.0 as f32,
+            SortKey::Time => self.time.second.0 as f32,
+            SortKey::Nps => self.nps.second.0.unwrap_or_default() as f32,
+            SortKey::Branching => self.branching_factor.second.0,
+            SortKey::Score => self.score.second.key() as f32,
         }
     }
 }
@@ -46,12 +120,21 @@ impl Add for Diff {
     fn add(self, rhs: Self) -> Self::Output {
         Self {
             position: String::new(),
+            id: None,
             depth: self.depth,
             nodes: self.nodes + rhs.nodes,
             time: self.time + rhs.time,
             nps: self.nps + rhs.nps,
             score: self.score + rhs.score,
             branching_factor: self.branching_factor + rhs.branching_factor,
+            ebf: self.ebf + rhs.ebf,
+            reached_depth: self.reached_depth + rhs.reached_depth,
+            seldepth: self.seldepth + rhs.seldepth,
+            hashfull: self.hashfull + rhs.hashfull,
+            tbhits: self.tbhits + rhs.tbhits,
+            time_to_depth: self.time_to_depth + rhs.time_to_depth,
+            best_move: BestMoveDiff::default(),
+            pv: PvDiff::default(),
         }
     }
 }
@@ -62,12 +145,21 @@ impl Div<usize> for Diff {
     fn div(self, rhs: usize) -> Self::Output {
         Self {
             position: self.position,
+            id: self.id,
             depth: self.depth,
             nodes: self.nodes / rhs,
             time: self.time / rhs,
             nps: self.nps / rhs,
             score: self.score / rhs,
             branching_factor: self.branching_factor / rhs,
+            ebf: self.ebf / rhs,
+            reached_depth: self.reached_depth / rhs,
+            seldepth: self.seldepth / rhs,
+            hashfull: self.hashfull / rhs,
+            tbhits: self.tbhits / rhs,
+            time_to_depth: self.time_to_depth / rhs,
+            best_move: self.best_move,
+            pv: self.pv,
         }
     }
 }
@@ -84,12 +176,23 @@ impl Extract for Diff {
 
         values.push(format!("{}", self.position.to_string().blue()));
 
+        if fields.label {
+            values.push(self.id.clone().unwrap_or_default())
+        }
+
+        // The averages row has no position, and no single depth either
+        if fields.depth && self.position.is_empty() {
+            values.push(String::new())
+        } else if fields.depth {
+            values.push(self.depth.to_string())
+        }
+
         if fields.nodes {
-            values.push(self.nodes.to_string())
+            values.push(fields.format(&self.nodes))
         }
 
         if fields.time {
-            values.push(self.time.to_string())
+            values.push(fields.format(&self.time))
         }
 
         if fields.nps {
@@ -100,111 +203,299 @@ impl Extract for Diff {
             values.push(self.branching_factor.to_string())
         }
 
+        if fields.ebf {
+            values.push(self.ebf.to_string())
+        }
+
+        if fields.reached_depth {
+            values.push(self.reached_depth.to_string())
+        }
+
+        if fields.seldepth {
+            values.push(self.seldepth.to_string())
+        }
+
+        if fields.hashfull {
+            values.push(self.hashfull.to_string())
+        }
+
+        if fields.tbhits {
+            values.push(self.tbhits.to_string())
+        }
+
+        if fields.time_to_depth {
+            values.push(fields.format(&self.time_to_depth))
+        }
+
         if fields.score {
             values.push(self.score.to_string())
         }
 
+        if fields.best_move {
+            values.push(self.best_move.to_string())
+        }
+
+        if fields.pv {
+            values.push(self.pv.to_string())
+        }
+
         values
     }
 }
 
-////////////////////////////////////////////////////////////////////////////////
-/// 
-/// Nodes
-///
-////////////////////////////////////////////////////////////////////////////////
-#[derive(PartialEq, Eq, Serialize, Deserialize, Copy, Clone, Default)]
-pub struct Nodes(pub u32);
+/// The metric that decides whether a diff counts as a regression
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum GateMetric {
+    Nps,
+    Nodes,
+    Time,
+}
 
-impl PartialOrd for Nodes {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(other.0.cmp(&self.0))
+impl From<GateMetric> for SortKey {
+    fn from(metric: GateMetric) -> Self {
+        match metric {
+            GateMetric::Nps => SortKey::Nps,
+            GateMetric::Nodes => SortKey::Nodes,
+            GateMetric::Time => SortKey::Time,
+        }
     }
 }
 
-impl Ord for Nodes {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        other.0.cmp(&self.0)
+impl Display for GateMetric {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GateMetric::Nps => write!(f, "nps"),
+            GateMetric::Nodes => write!(f, "nodes"),
+            GateMetric::Time => write!(f, "time"),
+        }
     }
 }
 
-impl Add for Nodes {
-    type Output = Self;
+/// Which direction of change makes a metric worse
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Worse {
+    Lower,
+    Higher,
+}
 
-    fn add(self, rhs: Self) -> Self::Output {
-        Self(self.0 + rhs.0)
+/// An override of the direction in which a metric gets worse, parsed from
+/// `metric=direction` (e.g., `nodes=lower`)
+#[derive(Debug, Clone, Copy)]
+pub struct WorseIs {
+    pub metric: SortKey,
+    pub worse: Worse,
+}
+
+impl FromStr for WorseIs {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let (metric, worse) = s.split_once('=')
+            .ok_or_else(|| anyhow!("Invalid override '{s}', expected metric=lower|higher"))?;
+
+        Ok(Self {
+            metric: SortKey::from_str(metric.trim(), true).map_err(|err| anyhow!(err))?,
+            worse: Worse::from_str(worse.trim(), true).map_err(|err| anyhow!(err))?,
+        })
     }
 }
 
-impl Div<usize> for Nodes {
-    type Output = Self;
+/// The overrides passed with `--worse-is`, read whenever a change gets 
+/// colored or gated on
+static WORSE_IS: OnceLock<Vec<WorseIs>> = OnceLock::new();
 
-    fn div(self, rhs: usize) -> Self::Output {
-        Self(self.0 / rhs as u32)
+/// Override the direction in which metrics get worse. Only the first call 
+/// has any effect.
+pub fn set_worse_is(overrides: Vec<WorseIs>) {
+    let _ = WORSE_IS.set(overrides);
+}
+
+/// The direction in which the metric gets worse. Spending more nodes, time,
+/// or a higher branching factor is worse by default, as is a lower nps or 
+/// score.
+pub fn worse_is(metric: SortKey) -> Worse {
+    let overridden = WORSE_IS.get()
+        .and_then(|overrides| overrides.iter().rev().find(|o| o.metric == metric));
+
+    if let Some(overridden) = overridden {
+        return overridden.worse;
+    }
+
+    match metric {
+        SortKey::Nodes | SortKey::Time | SortKey::Branching => Worse::Higher,
+        SortKey::Nps | SortKey::Score => Worse::Lower,
     }
 }
 
-impl Display for Nodes {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{} nodes", self.0)
+/// The color of the change in a metric: green when it got better, red when
+/// it got worse
+pub fn change_color<T: PartialOrd>(metric: SortKey, first: T, second: T) -> Color {
+    let worse = match worse_is(metric) {
+        Worse::Higher => second > first,
+        Worse::Lower => second < first,
+    };
+
+    if first == second {
+        Color::Black
+    } else if worse {
+        Color::Red
+    } else {
+        Color::Green
+    }
+}
+
+/// Compute the relative change between two values. Returns `None` when the
+/// baseline is zero, since the relative change is meaningless there.
+fn relative_change(first: f32, second: f32) -> Option<f32> {
+    if first == 0.0 {
+        None
+    } else {
+        Some((second - first) / first)
+    }
+}
+
+/// Add two relative changes, ignoring any that are missing
+fn add_relative(first: Option<f32>, second: Option<f32>) -> Option<f32> {
+    match (first, second) {
+        (Some(first), Some(second)) => Some(first + second),
+        (first, second) => first.or(second),
     }
 }
 
-#[derive(Default)]
-pub struct NodeDiff {
-    first: Nodes,
-    second: Nodes,
-    relative: f32,
+/// Average a sum of relative changes over the number of changes that went
+/// into it, rather than over every position, since positions with a zero 
+/// baseline don't have one
+fn average_relative(relative: Option<f32>, relatives: usize) -> Option<f32> {
+    relative.map(|relative| relative / relatives.max(1) as f32)
 }
 
-impl Add for NodeDiff {
+/// Format a relative change as a percentage, or `n/a` when it's missing
+pub fn format_relative(relative: Option<f32>) -> String {
+    match relative {
+        Some(relative) => format!("{:>+.2}%", 100.0 * relative),
+        None => String::from("n/a"),
+    }
+}
+
+/// Declare the diff between the values of a metric in two runs, along with
+/// the relative change between them. Summing diffs sums the values and the
+/// relative changes, and dividing by the number of positions averages them.
+macro_rules! metric_diff {
+    ($name:ident($metric:ty)) => {
+        #[derive(Default, Clone, Serialize)]
+        pub struct $name {
+            pub first: $metric,
+            pub second: $metric,
+            pub relative: Option<f32>,
+
+            /// The number of relative changes summed into `relative`, so
+            /// averaging leaves out the positions that don't have one
+            #[serde(skip)]
+            pub relatives: usize,
+        }
+
+        impl $name {
+            fn with_relative(first: $metric, second: $metric, relative: Option<f32>) -> Self {
+                Self { first, second, relative, relatives: usize::from(relative.is_some()) }
+            }
+        }
+
+        impl Add for $name {
+            type Output = Self;
+
+            fn add(self, rhs: Self) -> Self::Output {
+                Self {
+                    first: self.first + rhs.first,
+                    second: self.second + rhs.second,
+                    relative: add_relative(self.relative, rhs.relative),
+                    relatives: self.relatives + rhs.relatives,
+                }
+            }
+        }
+
+        impl Div<usize> for $name {
+            type Output = Self;
+
+            fn div(self, rhs: usize) -> Self::Output {
+                Self {
+                    first: self.first / rhs,
+                    second: self.second / rhs,
+                    relative: average_relative(self.relative, self.relatives),
+                    relatives: usize::from(self.relatives > 0),
+                }
+            }
+        }
+    };
+}
+
+////////////////////////////////////////////////////////////////////////////////
+/// 
+/// Nodes
+///
+////////////////////////////////////////////////////////////////////////////////
+#[derive(PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Copy, Clone, Default)]
+pub struct Nodes(pub u64);
+
+impl Add for Nodes {
     type Output = Self;
 
     fn add(self, rhs: Self) -> Self::Output {
-        Self {
-            first: self.first + rhs.first,
-            second: self.second + rhs.second,
-            relative: self.relative + rhs.relative,
-        }
+        Self(self.0.saturating_add(rhs.0))
     }
 }
 
-impl Div<usize> for NodeDiff {
+impl Div<usize> for Nodes {
     type Output = Self;
 
     fn div(self, rhs: usize) -> Self::Output {
-        Self {
-            first: self.first / rhs,
-            second: self.second / rhs,
-            relative: self.relative / rhs as f32,
+        Self(self.0 / rhs as u64)
+    }
+}
+
+/// Nodes are printed in full, or with a K/M/G suffix (e.g., `1.23G nodes`) 
+/// in the alternate form (`{:#}`)
+impl Display for Nodes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !f.alternate() {
+            return write!(f, "{} nodes", self.0);
+        }
+
+        let nodes = self.0 as f64;
+
+        match self.0 {
+            0..=999 => write!(f, "{} nodes", self.0),
+            1_000..=999_999 => write!(f, "{:.2}K nodes", nodes / 1e3),
+            1_000_000..=999_999_999 => write!(f, "{:.2}M nodes", nodes / 1e6),
+            _ => write!(f, "{:.2}G nodes", nodes / 1e9),
         }
     }
 }
 
+metric_diff!(NodeDiff(Nodes));
+
 impl NodeDiff {
     pub fn new(first: Nodes, second: Nodes) -> Self {
 
-        let relative = (second.0 as f32 - first.0 as f32) / first.0 as f32;
-        Self { first, second, relative }
+        let relative = relative_change(first.0 as f32, second.0 as f32);
+        Self::with_relative(first, second, relative)
     }
 }
 
 impl Display for NodeDiff {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        // NOTE: Custom definition of >/< !!!
-        let color = if self.second > self.first {
-            Color::Green
-        } else if self.second < self.first {
-            Color::Red
+        let color = change_color(SortKey::Nodes, self.first, self.second);
+
+        let (first, second) = if f.alternate() {
+            (format!("{:#}", self.first), format!("{:#}", self.second))
         } else {
-            Color::Black
+            (format!("{}", self.first), format!("{}", self.second))
         };
 
-        let first = format!("{}", self.first).color(Color::Black);
-        let second = format!("{}", self.second).color(color);
+        let first = first.color(Color::Black);
+        let second = second.color(color);
         let relative = format!(
             "({})", 
-            format!("{:>+.2}%", 100.0 * self.relative).color(color)
+            format_relative(self.relative).color(color)
         );
 
         write!(f, "{:>15} {:>15} {:>20}", first, second, relative)
@@ -216,24 +507,22 @@ impl Display for NodeDiff {
 /// Time
 ///
 ////////////////////////////////////////////////////////////////////////////////
-#[derive(PartialEq, Eq, Serialize, Deserialize, Copy, Clone, Default)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Copy, Clone, Default)]
 pub struct Time(pub u64);
 
+/// Times are printed in milliseconds, or in seconds and minutes (e.g., 
+/// `83.0s`, `1m23s`) in the alternate form (`{:#}`)
 impl Display for Time {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}ms", self.0)
-    }
-}
-
-impl PartialOrd for Time {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(other.0.cmp(&self.0))
-    }
-}
+        if !f.alternate() {
+            return write!(f, "{}ms", self.0);
+        }
 
-impl Ord for Time {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        other.0.cmp(&self.0)
+        match self.0 {
+            0..=999 => write!(f, "{}ms", self.0),
+            1_000..=59_999 => write!(f, "{:.1}s", self.0 as f64 / 1000.0),
+            _ => write!(f, "{}m{:02}s", self.0 / 60_000, self.0 / 1000 % 60),
+        }
     }
 }
 
@@ -241,7 +530,7 @@ impl Add for Time {
     type Output = Self;
 
     fn add(self, rhs: Self) -> Self::Output {
-        Self(self.0 + rhs.0)
+        Self(self.0.saturating_add(rhs.0))
     }
 }
 
@@ -253,110 +542,214 @@ impl Div<usize> for Time {
     }
 }
 
-#[derive(Default)]
-pub struct TimeDiff {
-    first: Time,
-    second: Time,
-    relative: f32,
-}
+metric_diff!(TimeDiff(Time));
 
 impl TimeDiff {
     pub fn new(first: Time, second: Time) -> Self {
-        let relative = (second.0 as f32 - first.0 as f32) / first.0 as f32;
-        Self { first, second, relative }
+        let relative = relative_change(first.0 as f32, second.0 as f32);
+        Self::with_relative(first, second, relative)
     }
 }
 
 impl Display for TimeDiff {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        // NOTE: Custom definition of >/< !!!
-        let color = if self.second > self.first {
-            Color::Green
-        } else if self.second < self.first {
-            Color::Red
+        let color = change_color(SortKey::Time, self.first, self.second);
+
+        let (first, second) = if f.alternate() {
+            (format!("{:#}", self.first), format!("{:#}", self.second))
         } else {
-            Color::Black
+            (format!("{}", self.first), format!("{}", self.second))
         };
 
-        let first = format!("{}", self.first).color(Color::Black);
-        let second = format!("{}", self.second).color(color);
+        let first = first.color(Color::Black);
+        let second = second.color(color);
         let relative = format!(
             "({})", 
-            format!("{:>+.2}%", 100.0 * self.relative).color(color)
+            format_relative(self.relative).color(color)
         );
 
         write!(f, "{:>7} {:>7} {:>20}", first, second, relative)
     }
 }
 
-impl Add for TimeDiff {
+////////////////////////////////////////////////////////////////////////////////
+/// 
+/// Nps
+///
+////////////////////////////////////////////////////////////////////////////////
+/// The search speed, in thousands of nodes per second. Unavailable when the
+/// engine didn't report the nodes or time it was derived from.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Copy, Clone, Default)]
+pub struct Nps(pub Option<u64>);
+
+impl Display for Nps {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            Some(nps) => write!(f, "{nps}knps"),
+            None => write!(f, "n/a"),
+        }
+    }
+}
+
+/// Add two speeds, ignoring any that are unavailable
+impl Add for Nps {
     type Output = Self;
 
     fn add(self, rhs: Self) -> Self::Output {
-        Self {
-            first: self.first + rhs.first,
-            second: self.second + rhs.second,
-            relative: self.relative + rhs.relative,
+        match (self.0, rhs.0) {
+            (Some(first), Some(second)) => Self(Some(first.saturating_add(second))),
+            (first, second) => Self(first.or(second)),
         }
     }
 }
 
-impl Div<usize> for TimeDiff {
+metric_diff!(NpsDiff(Nps));
+
+impl NpsDiff {
+    pub fn new(first: Nps, second: Nps) -> Self {
+        let relative = match (first.0, second.0) {
+            (Some(first), Some(second)) => relative_change(first as f32, second as f32),
+            _ => None,
+        };
+
+        Self::with_relative(first, second, relative)
+    }
+}
+
+impl Display for NpsDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let color = change_color(SortKey::Nps, self.first, self.second);
+
+        let first = format!("{}", self.first).color(Color::Black);
+        let second = format!("{}", self.second).color(color);
+        let relative = format!(
+            "({})", 
+            format_relative(self.relative).color(color)
+        );
+
+        write!(f, "{:>8} {:>8} {:>20}", first, second, relative)
+    }
+}
+
+impl Div<usize> for Nps {
     type Output = Self;
 
     fn div(self, rhs: usize) -> Self::Output {
-        Self {
-            first: self.first / rhs,
-            second: self.second / rhs,
-            relative: self.relative / rhs as f32,
+        Self(self.0.map(|nps| nps / rhs as u64))
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+/// 
+/// Branching factor
+///
+////////////////////////////////////////////////////////////////////////////////
+#[derive(PartialEq, PartialOrd, Serialize, Deserialize, Copy, Clone, Default)]
+pub struct BFactor(pub f32);
+
+impl Display for BFactor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !self.0.is_finite() {
+            return write!(f, "—");
         }
+
+        write!(f, "{:.2}", self.0)
+    }
+}
+
+impl Add for BFactor {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self(self.0 + rhs.0)
+    }
+}
+
+impl Div<usize> for BFactor {
+    type Output = Self;
+
+    fn div(self, rhs: usize) -> Self::Output {
+        Self(self.0 / rhs as f32)
+    }
+}
+
+metric_diff!(BFactorDiff(BFactor));
+
+impl BFactorDiff {
+    pub fn new(first: BFactor, second: BFactor) -> Self {
+        let relative = relative_change(first.0, second.0);
+        Self::with_relative(first, second, relative)
     }
 }
 
+impl Display for BFactorDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let color = change_color(SortKey::Branching, self.first, self.second);
+
+        let first = format!("{}", self.first).color(Color::Black);
+        let second = format!("{}", self.second).color(color);
+        let relative = format!(
+            "({})", 
+            format_relative(self.relative).color(color)
+        );
+
+        write!(f, "{:>5} {:>5} {:>20}", first, second, relative)
+    }
+}
 
 ////////////////////////////////////////////////////////////////////////////////
 /// 
-/// Nps
+/// Reached depth
 ///
 ////////////////////////////////////////////////////////////////////////////////
+/// The depth the engine reported last, which may differ from the requested 
+/// depth. Snapshots from before it was recorded have a reached depth of 0.
 #[derive(PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Copy, Clone, Default)]
-pub struct Nps(pub u32);
+pub struct ReachedDepth(pub usize);
 
-impl Display for Nps {
+impl Display for ReachedDepth {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}knps", self.0)
+        write!(f, "{}", self.0)
     }
 }
 
-impl Add for Nps {
+impl Add for ReachedDepth {
     type Output = Self;
 
     fn add(self, rhs: Self) -> Self::Output {
-        Self(self.0 + rhs.0)
+        Self(self.0.saturating_add(rhs.0))
     }
 }
 
-#[derive(Default)]
-pub struct NpsDiff {
-    first: Nps,
-    second: Nps,
-    relative: f32,
+impl Div<usize> for ReachedDepth {
+    type Output = Self;
+
+    fn div(self, rhs: usize) -> Self::Output {
+        Self(self.0 / rhs)
+    }
 }
 
-impl NpsDiff {
-    pub fn new(first: Nps, second: Nps) -> Self {
-        let relative = (second.0 as f32 - first.0 as f32) / first.0 as f32;
-        Self { first, second, relative }
+metric_diff!(ReachedDepthDiff(ReachedDepth));
+
+impl ReachedDepthDiff {
+    pub fn new(first: ReachedDepth, second: ReachedDepth) -> Self {
+        let relative = relative_change(first.0 as f32, second.0 as f32);
+        Self::with_relative(first, second, relative)
+    }
+
+    /// Whether both runs reached a different depth. Unknown depths don't 
+    /// count as a change.
+    pub fn changed(&self) -> bool {
+        self.first.0 != 0 && self.second.0 != 0 && self.first != self.second
     }
 }
 
-impl Display for NpsDiff {
+impl Display for ReachedDepthDiff {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        // NOTE: Custom definition of >/< !!!
-        let color = if self.second > self.first {
-            Color::Green
-        } else if self.second < self.first {
-            Color::Red
+        // Searching deeper in the same time is better, but so is searching 
+        // to the same depth faster, so just flag any change.
+        let color = if self.changed() {
+            Color::Yellow
         } else {
             Color::Black
         };
@@ -365,38 +758,99 @@ impl Display for NpsDiff {
         let second = format!("{}", self.second).color(color);
         let relative = format!(
             "({})", 
-            format!("{:>+.2}%", 100.0 * self.relative).color(color)
+            format_relative(self.relative).color(color)
         );
 
-        write!(f, "{:>8} {:>8} {:>20}", first, second, relative)
+        write!(f, "{:>3} {:>3} {:>15}", first, second, relative)
     }
 }
 
-impl Add for NpsDiff {
+////////////////////////////////////////////////////////////////////////////////
+/// 
+/// Seldepth
+///
+////////////////////////////////////////////////////////////////////////////////
+#[derive(PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Copy, Clone, Default)]
+pub struct Seldepth(pub usize);
+
+impl Display for Seldepth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Add for Seldepth {
     type Output = Self;
 
     fn add(self, rhs: Self) -> Self::Output {
-        Self {
-            first: self.first + rhs.first,
-            second: self.second + rhs.second,
-            relative: self.relative + rhs.relative,
-        }
+        Self(self.0.saturating_add(rhs.0))
     }
 }
 
-impl Div<usize> for NpsDiff {
+impl Div<usize> for Seldepth {
     type Output = Self;
 
     fn div(self, rhs: usize) -> Self::Output {
-        Self {
-            first: self.first / rhs,
-            second: self.second / rhs,
-            relative: self.relative / rhs as f32,
-        }
+        Self(self.0 / rhs)
     }
 }
 
-impl Div<usize> for Nps {
+metric_diff!(SeldepthDiff(Seldepth));
+
+impl SeldepthDiff {
+    pub fn new(first: Seldepth, second: Seldepth) -> Self {
+        let relative = relative_change(first.0 as f32, second.0 as f32);
+        Self::with_relative(first, second, relative)
+    }
+}
+
+impl Display for SeldepthDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // A deeper or shallower selective search isn't better or worse in 
+        // itself, so just flag any change.
+        let color = if self.second != self.first {
+            Color::Yellow
+        } else {
+            Color::Black
+        };
+
+        let first = format!("{}", self.first).color(Color::Black);
+        let second = format!("{}", self.second).color(color);
+        let relative = format!(
+            "({})", 
+            format_relative(self.relative).color(color)
+        );
+
+        write!(f, "{:>3} {:>3} {:>15}", first, second, relative)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+/// 
+/// Hashfull
+///
+////////////////////////////////////////////////////////////////////////////////
+/// How full the engine's hash table was, in permille, as reported in its 
+/// `info` lines. Stored as a `u32`, so summing a large suite doesn't overflow.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Copy, Clone, Default)]
+pub struct Hashfull(pub u32);
+
+/// Printed as a percentage, e.g., `42.5%`
+impl Display for Hashfull {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:.1}%", self.0 as f32 / 10.0)
+    }
+}
+
+impl Add for Hashfull {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self(self.0.saturating_add(rhs.0))
+    }
+}
+
+impl Div<usize> for Hashfull {
     type Output = Self;
 
     fn div(self, rhs: usize) -> Self::Output {
@@ -404,57 +858,82 @@ impl Div<usize> for Nps {
     }
 }
 
+metric_diff!(HashfullDiff(Hashfull));
+
+impl HashfullDiff {
+    pub fn new(first: Hashfull, second: Hashfull) -> Self {
+        let relative = relative_change(first.0 as f32, second.0 as f32);
+        Self::with_relative(first, second, relative)
+    }
+}
+
+impl Display for HashfullDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // A fuller hash table mostly follows from searching more nodes, so
+        // just flag any change.
+        let color = if self.second != self.first {
+            Color::Yellow
+        } else {
+            Color::Black
+        };
+
+        let first = format!("{}", self.first).color(Color::Black);
+        let second = format!("{}", self.second).color(color);
+        let relative = format!(
+            "({})", 
+            format_relative(self.relative).color(color)
+        );
+
+        write!(f, "{:>6} {:>6} {:>15}", first, second, relative)
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 /// 
-/// Branching factor
+/// TbHits
 ///
 ////////////////////////////////////////////////////////////////////////////////
-#[derive(PartialEq, PartialOrd, Serialize, Deserialize, Copy, Clone, Default)]
-pub struct BFactor(pub f32);
+/// The number of tablebase hits the engine reported
+#[derive(PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Copy, Clone, Default)]
+pub struct TbHits(pub u64);
 
-impl Display for BFactor {
+impl Display for TbHits {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:.2}", self.0)
+        write!(f, "{}", self.0)
     }
 }
 
-impl Add for BFactor {
+impl Add for TbHits {
     type Output = Self;
 
     fn add(self, rhs: Self) -> Self::Output {
-        Self(self.0 + rhs.0)
+        Self(self.0.saturating_add(rhs.0))
     }
 }
 
-impl Div<usize> for BFactor {
+impl Div<usize> for TbHits {
     type Output = Self;
 
     fn div(self, rhs: usize) -> Self::Output {
-        Self(self.0 / rhs as f32)
+        Self(self.0 / rhs as u64)
     }
 }
 
-#[derive(Default)]
-pub struct BFactorDiff {
-    first: BFactor,
-    second: BFactor,
-    relative: f32,
-}
+metric_diff!(TbHitsDiff(TbHits));
 
-impl BFactorDiff {
-    pub fn new(first: BFactor, second: BFactor) -> Self {
-        let relative = (second.0 - first.0) / first.0;
-        Self { first, second, relative }
+impl TbHitsDiff {
+    pub fn new(first: TbHits, second: TbHits) -> Self {
+        let relative = relative_change(first.0 as f32, second.0 as f32);
+        Self::with_relative(first, second, relative)
     }
 }
 
-impl Display for BFactorDiff {
+impl Display for TbHitsDiff {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        // NOTE: Custom definition of >/< !!!
-        let color = if self.second < self.first {
-            Color::Green
-        } else if self.second > self.first {
-            Color::Red
+        // Whether more tablebase hits are good depends on what's being 
+        // tested, so just flag any change.
+        let color = if self.second != self.first {
+            Color::Yellow
         } else {
             Color::Black
         };
@@ -463,57 +942,175 @@ impl Display for BFactorDiff {
         let second = format!("{}", self.second).color(color);
         let relative = format!(
             "({})", 
-            format!("{:>+.2}%", 100.0 * self.relative).color(color)
+            format_relative(self.relative).color(color)
         );
 
-        write!(f, "{:>5} {:>5} {:>20}", first, second, relative)
+        write!(f, "{:>8} {:>8} {:>15}", first, second, relative)
     }
 }
 
-impl Add for BFactorDiff {
+////////////////////////////////////////////////////////////////////////////////
+/// 
+/// Time to depth
+///
+////////////////////////////////////////////////////////////////////////////////
+/// The time at which the engine first reported the depth passed with 
+/// `--target-depth`. Unavailable when it never got there.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Copy, Clone, Default)]
+pub struct TimeToDepth(pub Option<Time>);
+
+impl TimeToDepth {
+    pub fn is_none(&self) -> bool {
+        self.0.is_none()
+    }
+}
+
+impl Display for TimeToDepth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            Some(time) if f.alternate() => write!(f, "{time:#}"),
+            Some(time) => write!(f, "{time}"),
+            None => write!(f, "—"),
+        }
+    }
+}
+
+/// Add two times, ignoring any that are unavailable
+impl Add for TimeToDepth {
     type Output = Self;
 
     fn add(self, rhs: Self) -> Self::Output {
-        Self {
-            first: self.first + rhs.first,
-            second: self.second + rhs.second,
-            relative: self.relative + rhs.relative,
+        match (self.0, rhs.0) {
+            (Some(first), Some(second)) => Self(Some(first + second)),
+            (first, second) => Self(first.or(second)),
         }
     }
 }
 
-impl Div<usize> for BFactorDiff {
+impl Div<usize> for TimeToDepth {
     type Output = Self;
 
     fn div(self, rhs: usize) -> Self::Output {
-        Self {
-            first: self.first / rhs,
-            second: self.second / rhs,
-            relative: self.relative / rhs as f32,
-        }
+        Self(self.0.map(|time| time / rhs))
     }
 }
 
+metric_diff!(TimeToDepthDiff(TimeToDepth));
+
+impl TimeToDepthDiff {
+    pub fn new(first: TimeToDepth, second: TimeToDepth) -> Self {
+        let relative = match (first.0, second.0) {
+            (Some(first), Some(second)) => relative_change(first.0 as f32, second.0 as f32),
+            _ => None,
+        };
+
+        Self::with_relative(first, second, relative)
+    }
+}
+
+impl Display for TimeToDepthDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let color = change_color(SortKey::Time, self.first, self.second);
+
+        let (first, second) = if f.alternate() {
+            (format!("{:#}", self.first), format!("{:#}", self.second))
+        } else {
+            (format!("{}", self.first), format!("{}", self.second))
+        };
+
+        let first = first.color(Color::Black);
+        let second = second.color(color);
+        let relative = format!(
+            "({})", 
+            format_relative(self.relative).color(color)
+        );
+
+        write!(f, "{:>7} {:>7} {:>20}", first, second, relative)
+    }
+}
 
 ////////////////////////////////////////////////////////////////////////////////
 /// 
 /// Score
 ///
 ////////////////////////////////////////////////////////////////////////////////
-#[derive(PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Copy, Clone, Default)]
-pub struct Score(pub i32);
+/// A score reported by the engine, either in centipawns, or as a mate 
+/// distance.
+///
+/// Centipawn scores are (de)serialized as plain numbers, so older snapshots 
+/// still load.
+#[derive(PartialEq, Eq, Serialize, Deserialize, Copy, Clone, Debug)]
+#[serde(untagged)]
+pub enum Score {
+    Cp(i32),
+
+    /// Mate in the given number of moves. Negative if the engine is the one
+    /// getting mated.
+    Mate { mate: i32 },
+}
+
+impl Score {
+    /// Upper bound on centipawn scores, used to rank mates above any regular
+    /// score
+    const MATE: i64 = 1_000_000;
+
+    /// Map the score onto a single number line, for comparing scores
+    pub fn key(self) -> i64 {
+        match self {
+            Score::Cp(cp) => cp as i64,
+            Score::Mate { mate } if mate > 0 => Self::MATE - mate as i64,
+            Score::Mate { mate } => -Self::MATE - mate as i64,
+        }
+    }
+
+    /// Average a sum of scores over the number of centipawn scores that went
+    /// into it, since mate scores are left out of the sum
+    pub fn average(self, cps: usize) -> Self {
+        match self {
+            Score::Cp(cp) => Score::Cp(cp / cps.max(1) as i32),
+            mate => mate,
+        }
+    }
+}
+
+impl Default for Score {
+    fn default() -> Self {
+        Score::Cp(0)
+    }
+}
+
+impl PartialOrd for Score {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Score {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.key().cmp(&other.key())
+    }
+}
 
 impl Display for Score {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:+.2}", self.0 as f32/ 100.0)
+        match self {
+            Score::Cp(cp) => write!(f, "{:+.2}", *cp as f32/ 100.0),
+            Score::Mate { mate } => write!(f, "#{mate}"),
+        }
     }
 }
 
+/// Averaging mate distances is meaningless, so mate scores are left out of 
+/// any sums.
 impl Add for Score {
     type Output = Self;
 
     fn add(self, rhs: Self) -> Self::Output {
-        Self(self.0 + rhs.0)
+        match (self, rhs) {
+            (Score::Cp(first), Score::Cp(second)) => Score::Cp(first.saturating_add(second)),
+            (Score::Cp(cp), _) | (_, Score::Cp(cp)) => Score::Cp(cp),
+            _ => Score::default(),
+        }
     }
 }
 
@@ -521,35 +1118,47 @@ impl Div<usize> for Score {
     type Output = Self;
 
     fn div(self, rhs: usize) -> Self::Output {
-        Self(self.0 / rhs as i32)
+        match self {
+            Score::Cp(cp) => Score::Cp(cp / rhs as i32),
+            mate => mate,
+        }
     }
 }
 
 
-#[derive(Default)]
+#[derive(Default, Clone, Serialize)]
 pub struct ScoreDiff {
-    first: Score,
-    second: Score,
-    relative: f32,
+    pub first: Score,
+    pub second: Score,
+    pub relative: Option<f32>,
+
+    /// The number of relative changes summed into `relative`, so averaging
+    /// leaves out the positions that don't have one
+    #[serde(skip)]
+    pub relatives: usize,
+
+    /// The number of centipawn scores summed into `first` and `second`, so
+    /// averaging leaves out the mate scores
+    #[serde(skip)]
+    pub cps: (usize, usize),
 }
 
 impl ScoreDiff {
     pub fn new(first: Score, second: Score) -> Self {
-        let relative = (second.0 as f32 - first.0 as f32) / first.0 as f32;
-        Self { first, second, relative }
+        // There's no meaningful relative change between mate scores
+        let relative = match (first, second) {
+            (Score::Cp(first), Score::Cp(second)) => relative_change(first as f32, second as f32),
+            _ => None,
+        };
+
+        let cps = (usize::from(matches!(first, Score::Cp(_))), usize::from(matches!(second, Score::Cp(_))));
+        Self { first, second, relative, relatives: usize::from(relative.is_some()), cps }
     }
 }
 
 impl Display for ScoreDiff {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        // NOTE: Custom definition of >/< !!!
-        let color = if self.second > self.first {
-            Color::Green
-        } else if self.second < self.first {
-            Color::Red
-        } else {
-            Color::Black
-        };
+        let color = change_color(SortKey::Score, self.first, self.second);
 
         let first = format!("{}", self.first).color(Color::Black);
         let second = format!("{}", self.second).color(color);
@@ -565,19 +1174,217 @@ impl Add for ScoreDiff {
         Self {
             first: self.first + rhs.first,
             second: self.second + rhs.second,
-            relative: self.relative + rhs.relative,
+            relative: add_relative(self.relative, rhs.relative),
+            relatives: self.relatives + rhs.relatives,
+            cps: (self.cps.0 + rhs.cps.0, self.cps.1 + rhs.cps.1),
         }
     }
 }
 
+/// Only the centipawn scores count towards the average, so the divisor is 
+/// ignored
 impl Div<usize> for ScoreDiff {
     type Output = Self;
 
-    fn div(self, rhs: usize) -> Self::Output {
+    fn div(self, _rhs: usize) -> Self::Output {
         Self {
-            first: self.first / rhs,
-            second: self.second / rhs,
-            relative: self.relative / rhs as f32,
+            first: self.first.average(self.cps.0),
+            second: self.second.average(self.cps.1),
+            relative: average_relative(self.relative, self.relatives),
+            relatives: usize::from(self.relatives > 0),
+            cps: (usize::from(self.cps.0 > 0), usize::from(self.cps.1 > 0)),
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+/// 
+/// Best move
+///
+////////////////////////////////////////////////////////////////////////////////
+#[derive(Default, Clone, Serialize)]
+pub struct BestMoveDiff {
+    pub first: String,
+    pub second: String,
+}
+
+impl BestMoveDiff {
+    pub fn new(first: &str, second: &str) -> Self {
+        Self { first: first.to_string(), second: second.to_string() }
+    }
+}
+
+impl Display for BestMoveDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let color = if self.first == self.second {
+            Color::Black
+        } else {
+            Color::Red
+        };
+
+        let first = self.first.color(Color::Black);
+        let second = self.second.color(color);
+
+        write!(f, "{:>6} {:>6}", first, second)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+/// 
+/// Principal variation
+///
+////////////////////////////////////////////////////////////////////////////////
+#[derive(Default, Clone, Serialize)]
+pub struct PvDiff {
+    pub first: Vec<String>,
+    pub second: Vec<String>,
+}
+
+impl PvDiff {
+    pub fn new(first: &[String], second: &[String]) -> Self {
+        Self { first: first.to_vec(), second: second.to_vec() }
+    }
+}
+
+impl Display for PvDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let first = self.first.join(" ");
+
+        // Only print the PV once when it didn't change
+        if self.first == self.second {
+            return write!(f, "{}", first.color(Color::Black));
+        }
+
+        let second = self.second.join(" ");
+
+        write!(f, "{} {} {}", first.color(Color::Black), "→".color(Color::Red), second.color(Color::Red))
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+///
+/// Tests
+///
+////////////////////////////////////////////////////////////////////////////////
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Average a list of diffs the way the summary row does
+    fn average<T: Add<Output = T> + Div<usize, Output = T>>(diffs: Vec<T>) -> T {
+        let count = diffs.len();
+        diffs.into_iter().reduce(|sum, diff| sum + diff).unwrap() / count
+    }
+
+    fn assert_relative(relative: Option<f32>, expected: f32) {
+        let relative = relative.expect("expected a relative change");
+        assert!((relative - expected).abs() < 1e-6, "{relative} != {expected}");
+    }
+
+    /// Average a position with a zero baseline, which has no relative change,
+    /// with a position that went up by 10%
+    fn zero_baseline<T, D>(new: fn(T, T) -> D, metric: fn(u64) -> T) -> D
+    where D: Add<Output = D> + Div<usize, Output = D> {
+        average(vec![
+            new(metric(0), metric(500)),
+            new(metric(1000), metric(1100)),
+        ])
+    }
+
+    #[test]
+    fn average_skips_zero_baseline() {
+        let cases = [
+            ("nodes", zero_baseline(NodeDiff::new, Nodes).relative),
+            ("time", zero_baseline(TimeDiff::new, Time).relative),
+            ("nps", zero_baseline(NpsDiff::new, |nps| Nps(Some(nps))).relative),
+            ("bfactor", zero_baseline(BFactorDiff::new, |bf| BFactor(bf as f32)).relative),
+            ("depth", zero_baseline(ReachedDepthDiff::new, |depth| ReachedDepth(depth as usize)).relative),
+            ("seldepth", zero_baseline(SeldepthDiff::new, |depth| Seldepth(depth as usize)).relative),
+            ("hashfull", zero_baseline(HashfullDiff::new, |hashfull| Hashfull(hashfull as u32)).relative),
+            ("tbhits", zero_baseline(TbHitsDiff::new, TbHits).relative),
+        ];
+
+        for (metric, relative) in cases {
+            let relative = relative.unwrap_or_else(|| panic!("{metric} has no relative change"));
+            assert!((relative - 0.1).abs() < 1e-6, "{metric}: {relative} != 0.1");
         }
     }
+
+    #[test]
+    fn nps_average_skips_missing_baseline() {
+        let diff = average(vec![
+            NpsDiff::new(Nps(None), Nps(Some(500))),
+            NpsDiff::new(Nps(Some(1000)), Nps(Some(1100))),
+        ]);
+
+        assert_relative(diff.relative, 0.1);
+    }
+
+    #[test]
+    fn time_to_depth_average_skips_missing_baseline() {
+        let diff = average(vec![
+            TimeToDepthDiff::new(TimeToDepth(None), TimeToDepth(Some(Time(50)))),
+            TimeToDepthDiff::new(TimeToDepth(Some(Time(0))), TimeToDepth(Some(Time(50)))),
+            TimeToDepthDiff::new(TimeToDepth(Some(Time(100))), TimeToDepth(Some(Time(110)))),
+        ]);
+
+        assert_relative(diff.relative, 0.1);
+    }
+
+    #[test]
+    fn score_average_skips_zero_baseline() {
+        let diff = average(vec![
+            ScoreDiff::new(Score::Cp(0), Score::Cp(50)),
+            ScoreDiff::new(Score::Mate { mate: 3 }, Score::Mate { mate: 2 }),
+            ScoreDiff::new(Score::Cp(100), Score::Cp(110)),
+        ]);
+
+        assert_relative(diff.relative, 0.1);
+    }
+
+    #[test]
+    fn average_without_any_relative_change_is_missing() {
+        let diff = average(vec![
+            NodeDiff::new(Nodes(0), Nodes(500)),
+            NodeDiff::new(Nodes(0), Nodes(700)),
+        ]);
+
+        assert!(diff.relative.is_none());
+    }
+
+    #[test]
+    fn metrics_sum_past_u32_max() {
+        // A suite of long searches adds up to more than fits in a u32
+        let diffs = vec![NodeDiff::new(Nodes(3_000_000_000), Nodes(3_300_000_000)); 3];
+        let total = diffs.iter().cloned().reduce(|sum, diff| sum + diff).unwrap();
+
+        assert_eq!(total.first.0, 9_000_000_000);
+        assert_eq!(total.second.0, 9_900_000_000);
+
+        let diff = average(diffs);
+        assert_eq!(diff.first.0, 3_000_000_000);
+        assert_eq!(diff.second.0, 3_300_000_000);
+
+        let nps = [Nps(Some(3_000_000_000)); 3];
+        let total = nps.into_iter().reduce(|sum, nps| sum + nps).unwrap();
+
+        assert_eq!(total.0, Some(9_000_000_000));
+        assert_eq!((total / 3).0, Some(3_000_000_000));
+    }
+
+    #[test]
+    fn score_average_leaves_out_mates() {
+        let diff = average(vec![
+            ScoreDiff::new(Score::Cp(100), Score::Mate { mate: 3 }),
+            ScoreDiff::new(Score::Mate { mate: 5 }, Score::Cp(40)),
+            ScoreDiff::new(Score::Cp(200), Score::Cp(60)),
+        ]);
+
+        assert_eq!(diff.first, Score::Cp(150));
+        assert_eq!(diff.second, Score::Cp(50));
+
+        // Nothing to average but mates
+        let diff = average(vec![ScoreDiff::new(Score::Mate { mate: 3 }, Score::Mate { mate: 2 })]);
+        assert_eq!(diff.first, Score::Mate { mate: 3 });
+    }
 }
@@ -1,16 +1,48 @@
-use std::io::{ BufRead, BufReader, BufWriter, Write };
+use std::fmt::Display;
+use std::fs::{File, OpenOptions};
+use std::io::{ BufRead, BufReader, BufWriter, ErrorKind, Write };
 use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
 
-use crate::search_result::SearchResult;
+use crate::diff::TimeToDepth;
+use crate::info::Info;
+use crate::interrupt;
+use crate::search_result::{Progress, PvLine, SearchResult};
+use crate::snapshot::EngineId;
 
 use simbelmyne_chess::board::Board;
 use simbelmyne_uci::client::UciClientMessage;
-use simbelmyne_uci::engine::UciEngineMessage;
-use simbelmyne_uci::search_info::SearchInfo;
 use simbelmyne_uci::time_control::TimeControl;
-use std::process::{Child, ChildStdin, ChildStdout, Command};
+use std::process::{Child, ChildStderr, ChildStdin, ChildStdout, Command};
 use std::process::Stdio;
+use std::str::FromStr;
 use anyhow::anyhow;
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+
+/// How often a search checks whether the run was interrupted
+const INTERRUPT_POLL: Duration = Duration::from_millis(100);
+
+/// How long an engine gets to send its best move after a timed out search is
+/// stopped, before it's killed
+const STOP_GRACE: Duration = Duration::from_millis(500);
+
+/// How much of the UCI conversation to echo to stderr
+static VERBOSITY: OnceLock<u8> = OnceLock::new();
+
+/// Echo the UCI conversation with every engine to stderr: the messages sent
+/// to the engine, and the UCI messages it sends back at level 1, and every
+/// line of its output at level 2. Only the first call has any effect.
+pub fn set_verbosity(level: u8) {
+    let _ = VERBOSITY.set(level);
+}
+
+fn verbosity() -> u8 {
+    VERBOSITY.get().copied().unwrap_or_default()
+}
 
 #[allow(dead_code)]
 pub struct Engine {
@@ -18,118 +50,790 @@ pub struct Engine {
     process: Child,
     stdin: UciWriter,
     stdout: UciReader,
+
+    /// The name and author the engine reported during the handshake
+    id: EngineId,
+
+    /// The names of the options the engine advertised during the handshake
+    options: Vec<String>,
+
+    /// The position currently being searched, used to tag the engine log
+    position: Arc<Mutex<String>>,
+
+    /// Whether to keep the hash table between positions, by only sending 
+    /// `ucinewgame` before the first one
+    shared_hash: bool,
+
+    /// Whether `ucinewgame` was sent yet
+    in_game: bool,
+
+    /// Whether to keep every iteration the engine reports in the result, 
+    /// rather than only the final one
+    record_progress: bool,
+
+    /// The depth to time the searches to, if any
+    target_depth: Option<usize>,
+
+    /// How long the engine took to start up
+    startup: Startup,
+}
+
+/// How long an engine took to get ready after being spawned
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Startup {
+    /// Until the engine replied `uciok`
+    pub uciok: Duration,
+
+    /// Until the engine replied `readyok` to the first `isready`
+    pub readyok: Duration,
 }
 
 impl Engine {
-    pub fn new(path: &Path) -> anyhow::Result<Self> {
+    /// Start the engine with the given command-line arguments, and perform 
+    /// the UCI handshake. The engine is started in `cwd` if provided, or the
+    /// current directory otherwise. If a log path is provided, the engine's 
+    /// stderr output gets appended to it.
+    pub fn new(
+        path: &Path, 
+        args: &[String], 
+        cwd: Option<&Path>,
+        log: Option<&Path>
+    ) -> anyhow::Result<Self> {
         let path = path.to_owned();
-        let mut process = Command::new(&path)
+        let stderr = if log.is_some() { Stdio::piped() } else { Stdio::null() };
+
+        let mut command = Command::new(&path);
+
+        if let Some(cwd) = cwd {
+            if !cwd.is_dir() {
+                return Err(anyhow!("Engine working directory {} doesn't exist", cwd.display()));
+            }
+
+            // Relative paths are ambiguous once the working directory changes,
+            // so resolve them against our own. Bare names are looked up on
+            // the PATH, so leave those alone.
+            if path.components().count() > 1 {
+                command = Command::new(path.canonicalize()?);
+            }
+
+            command.current_dir(cwd);
+        }
+
+        // Keep Ctrl-C from reaching the engine, so the run can stop it on 
+        // its own terms
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(&mut command, 0);
+
+        let spawned = Instant::now();
+
+        let mut process = command
+            .args(args)
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
-            .stderr(Stdio::null())
+            .stderr(stderr)
             .spawn()?;
 
+        let position = Arc::new(Mutex::new(String::new()));
+
+        if let Some(log) = log {
+            let file = OpenOptions::new().create(true).append(true).open(log)
+                .map_err(|err| anyhow!("Failed to open engine log {}: {err}", log.display()))?;
+
+            let stderr = process.stderr.take()
+                .ok_or_else(|| anyhow!("Failed to attach to stderr"))?;
+
+            spawn_logger(stderr, file, position.clone());
+        }
+
         let stdin = process.stdin.take()
             .ok_or_else(|| anyhow!("Failed to attach to stdin"))?;
 
         let stdout = process.stdout.take()
             .ok_or_else(|| anyhow!("Failed to attach to stdout"))?;
 
-        let writer = UciWriter::new(stdin);
-        let reader = UciReader::new(stdout);
+        let writer = UciWriter::new(stdin, process.id());
+        let reader = UciReader::new(stdout, process.id());
 
-        let mut engine = Self { path, process, stdin: writer, stdout: reader };
+        let mut engine = Self { 
+            path, 
+            process, 
+            stdin: writer, 
+            stdout: reader, 
+            id: EngineId::default(),
+            options: Vec::new(),
+            position,
+            shared_hash: false,
+            in_game: false,
+            record_progress: false,
+            target_depth: None,
+            startup: Startup::default(),
+        };
 
         // Start the engine in UCI mode
         engine.send(UciClientMessage::Uci)?;
 
-        for msg in &mut engine.stdout {
-            if let UciEngineMessage::UciOk = msg {
+        while let Ok(Some(line)) = engine.stdout.next_line_until(None) {
+            // `simbelmyne_uci` doesn't parse option lines, so pick out the
+            // names ourselves
+            if let Some(name) = parse_option_name(&line) {
+                engine.options.push(name);
+            }
+
+            if let Some(name) = line.trim().strip_prefix("id name ") {
+                engine.id.name = Some(name.trim().to_string());
+            }
+
+            if let Some(author) = line.trim().strip_prefix("id author ") {
+                engine.id.author = Some(author.trim().to_string());
+            }
+
+            if let EngineMessage::UciOk = parse_message(&line) {
+                engine.startup.uciok = spawned.elapsed();
                 break;
             }
         }
 
+        engine.wait_ready()?;
+        engine.startup.readyok = spawned.elapsed();
+
         Ok(engine)
     }
 
+    /// The location of the engine binary
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The name and author the engine reported
+    pub fn id(&self) -> &EngineId {
+        &self.id
+    }
+
+    /// How long the engine took to start up
+    pub fn startup(&self) -> Startup {
+        self.startup
+    }
+
     pub fn send(&mut self, msg: UciClientMessage) -> anyhow::Result<()> {
         self.stdin.write(msg)
     }
 
+    /// Check whether the engine advertised an option with the given name.
+    /// Option names are case-insensitive.
+    pub fn has_option(&self, name: &str) -> bool {
+        self.options.iter().any(|option| option.eq_ignore_ascii_case(name))
+    }
+
+    pub fn set_option(&mut self, name: &str, value: &str) -> anyhow::Result<()> {
+        self.send(UciClientMessage::SetOption(name.to_string(), value.to_string()))
+    }
+
+    /// Trigger a button option, which doesn't take a value
+    pub fn press_button(&mut self, name: &str) -> anyhow::Result<()> {
+        // `UciClientMessage::SetOption` always includes a value
+        self.stdin.write_line(&format!("setoption name {name}"))
+    }
+
+    /// Check whether the engine process is still up and talking to us
+    pub fn is_alive(&mut self) -> bool {
+        !self.stdout.closed && matches!(self.process.try_wait(), Ok(None))
+    }
+
+    /// Block until the engine is done processing any earlier commands
+    pub fn wait_ready(&mut self) -> anyhow::Result<()> {
+        self.send(UciClientMessage::IsReady)?;
+
+        while let Ok(Some(line)) = self.stdout.next_line_until(None) {
+            if let EngineMessage::ReadyOk = parse_message(&line) {
+                return Ok(());
+            }
+        }
+
+        Err(anyhow!("Engine exited before responding to isready"))
+    }
+
+    /// Keep the hash table between positions, rather than starting a new 
+    /// game (which clears it, for most engines) before every position
+    pub fn share_hash(&mut self) {
+        self.shared_hash = true;
+    }
+
+    /// Keep every iteration the engine reports during a search, in the
+    /// `progress` of the result
+    pub fn record_progress(&mut self) {
+        self.record_progress = true;
+    }
+
+    /// Record when every search first reaches the given depth, in the 
+    /// `time_to_depth` of the result
+    pub fn set_target_depth(&mut self, depth: usize) {
+        self.target_depth = Some(depth);
+    }
+
     pub fn set_position(&mut self, board: Board) -> anyhow::Result<()> {
-        self.send(UciClientMessage::UciNewGame)?;
+        if let Ok(mut position) = self.position.lock() {
+            *position = board.to_fen();
+        }
+
+        if !self.shared_hash || !self.in_game {
+            self.send(UciClientMessage::UciNewGame)?;
+            self.wait_ready()?;
+            self.in_game = true;
+        }
+
         self.send(UciClientMessage::Position(board, Vec::new()))?;
         Ok(())
 
     }
 
-    pub fn search(&mut self, board: Board, depth: usize) -> anyhow::Result<SearchResult> {
-        let mut latest_info: Option<SearchInfo> = None;
+    /// Search the board within the requested limit. If a timeout is provided, 
+    /// and the engine doesn't report a best move in time, the search is 
+    /// stopped and an error is returned.
+    ///
+    /// When searching to a fixed depth, that depth is recorded in the result.
+    /// Otherwise, the result holds the last depth the engine reported.
+    ///
+    /// When `searchmoves` isn't empty, the engine only considers those moves
+    /// at the root.
+    pub fn search(
+        &mut self, 
+        board: Board, 
+        tc: TimeControl, 
+        searchmoves: &[String],
+        timeout: Option<Duration>
+    ) -> anyhow::Result<SearchResult> {
+        let mut latest_info: Option<Info> = None;
+        let mut lines: Vec<Info> = Vec::new();
+        let mut progress: Vec<Progress> = Vec::new();
 
         self.set_position(board)?;
-        self.send(UciClientMessage::Go(TimeControl::Depth(depth)))?;
 
-        for msg in &mut self.stdout {
-            match msg {
-                UciEngineMessage::Info(info) => {
+        if searchmoves.is_empty() {
+            self.send(UciClientMessage::Go(tc))?;
+        } else {
+            // `UciClientMessage::Go` has no way to pass searchmoves
+            self.stdin.write_line(&format!("go {tc} searchmoves {}", searchmoves.join(" ")))?;
+        }
+
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+
+        let best_move = loop {
+            // Wake up every now and then to check for Ctrl-C
+            let poll = Instant::now() + INTERRUPT_POLL;
+            let poll = deadline.map_or(poll, |deadline| deadline.min(poll));
+
+            let line = match self.stdout.next_line_until(Some(poll)) {
+                Ok(Some(line)) => line,
+                Ok(None) => {
+                    return Err(anyhow!("Engine exited while searching {}", board.to_fen()));
+                },
+                Err(_) if interrupt::interrupted() => {
+                    self.send(UciClientMessage::Stop)?;
+                    return Err(anyhow!("Interrupted while searching {}", board.to_fen()));
+                },
+                Err(_) if deadline.is_none_or(|deadline| Instant::now() < deadline) => continue,
+                Err(_) => {
+                    self.abandon_search()?;
+
+                    return Err(SearchTimeout {
+                        timeout: timeout.unwrap_or_default(),
+                        fen: board.to_fen(),
+                    }.into());
+                }
+            };
+
+            let info = match parse_message(&line) {
+                EngineMessage::Info(info) => info,
+                EngineMessage::BestMove(mv) => break mv,
+                _ => continue,
+            };
+
+            match info.multipv {
+                _ if info.string.is_some() => {},
+                None | Some(0) | Some(1) => {
+                    // Only the lines with a score mark the end of an 
+                    // iteration, the rest are `currmove` updates and the like
+                    if info.score.is_some() {
+                        progress.push(Progress::from(info.clone()));
+                    }
+
                     latest_info = Some(info);
                 },
 
-                UciEngineMessage::BestMove(_) => {
-                    break;
+                // Keep track of the latest info for every secondary line, 
+                // skipping the `currmove` updates that don't carry a score
+                Some(idx) => {
+                    if lines.len() < idx - 1 {
+                        lines.resize(idx - 1, Info::default());
+                    }
+
+                    if info.score.is_some() {
+                        lines[idx - 2] = info;
+                    }
+                }
+            }
+        };
+
+        let latest_info = latest_info.unwrap_or_default();
+
+        let depth = match tc {
+            TimeControl::Depth(depth) => depth,
+            _ => latest_info.depth.unwrap_or_default(),
+        };
+
+        let mut result = SearchResult::new(board, depth, &latest_info, best_move);
+        result.lines = lines.into_iter().map(PvLine::from).collect();
+        result.searchmoves = searchmoves.to_vec();
+        result.ebf = SearchResult::effective_branching_factor(&progress)
+            .unwrap_or(result.branching_factor);
+
+        result.time_to_depth = TimeToDepth(self.target_depth.and_then(|depth| progress.iter()
+            .find(|iteration| iteration.depth >= depth)
+            .map(|iteration| iteration.time)
+        ));
+
+        if self.record_progress {
+            result.progress = progress;
+        }
+
+        Ok(result)
+    }
+
+    /// Stop a search that ran out of time. The best move the engine sends in
+    /// reply is read and thrown away, so it doesn't get mistaken for the 
+    /// result of the next search. An engine that doesn't reply in time is 
+    /// killed, so it doesn't count as alive anymore.
+    fn abandon_search(&mut self) -> anyhow::Result<()> {
+        self.send(UciClientMessage::Stop)?;
+
+        let deadline = Instant::now() + STOP_GRACE;
+
+        while let Ok(Some(line)) = self.stdout.next_line_until(Some(deadline)) {
+            if let EngineMessage::BestMove(_) = parse_message(&line) {
+                return Ok(());
+            }
+        }
+
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+
+        Ok(())
+    }
+
+    /// Run a perft on the board with the given (non-standard) command, and
+    /// return the number of nodes the engine counted. The engine is expected
+    /// to finish by printing `Nodes searched: <n>`, like Stockfish does.
+    pub fn perft(
+        &mut self, 
+        board: Board, 
+        command: &str, 
+        timeout: Option<Duration>
+    ) -> anyhow::Result<u64> {
+        self.set_position(board)?;
+        self.stdin.write_line(command)?;
+
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+
+        loop {
+            let line = match self.stdout.next_line_until(deadline) {
+                Ok(Some(line)) => line,
+                Ok(None) => {
+                    return Err(anyhow!("Engine exited while running perft on {}", board.to_fen()));
                 },
+                Err(_) => {
+                    return Err(anyhow!(
+                        "Engine timed out after {}ms while running perft on {}", 
+                        timeout.unwrap_or_default().as_millis(),
+                        board.to_fen()
+                    ));
+                }
+            };
 
-                _ => {}
+            if let Some(nodes) = parse_perft_nodes(&line) {
+                return Ok(nodes);
             }
         }
+    }
+}
 
-        let latest_info = latest_info.unwrap_or_default();
+impl Drop for Engine {
+    /// Ask the engine to quit, and kill it if it doesn't exit in time, so we
+    /// don't leave stray processes behind when a run errors out.
+    fn drop(&mut self) {
+        const GRACE_PERIOD: Duration = Duration::from_millis(500);
+
+        // The writer is flushed after every message
+        let _ = self.send(UciClientMessage::Quit);
+
+        let deadline = Instant::now() + GRACE_PERIOD;
+
+        while Instant::now() < deadline {
+            if let Ok(Some(_)) = self.process.try_wait() {
+                return;
+            }
+
+            thread::sleep(Duration::from_millis(10));
+        }
 
-        Ok(SearchResult::new(
-            board, 
-            latest_info.nodes.unwrap_or_default(), 
-            latest_info.time.unwrap_or_default(), 
-            latest_info.score.unwrap_or_default(),
-            depth
-        ))
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+    }
+}
+
+/// The error returned when the engine doesn't finish a search within the
+/// timeout
+#[derive(Debug)]
+pub struct SearchTimeout {
+    pub timeout: Duration,
+    pub fen: String,
+}
+
+impl Display for SearchTimeout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Engine timed out after {}ms while searching {}", self.timeout.as_millis(), self.fen)
+    }
+}
+
+impl std::error::Error for SearchTimeout {}
+
+/// A UCI option to set on the engine, parsed from `name=value`, or just 
+/// `name` for button options.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EngineOption {
+    pub name: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+}
+
+impl FromStr for EngineOption {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let (name, value) = match s.split_once('=') {
+            Some((name, value)) => (name, Some(value.trim().to_string())),
+            None => (s, None),
+        };
+
+        let name = name.trim();
+
+        if name.is_empty() {
+            return Err(anyhow!("Invalid option '{s}', expected name=value"));
+        }
+
+        Ok(Self { name: name.to_string(), value })
+    }
+}
+
+impl Display for EngineOption {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.value {
+            Some(value) => write!(f, "{}={}", self.name, value),
+            None => write!(f, "{}", self.name),
+        }
     }
 }
 
 struct UciWriter {
-    writer: BufWriter<ChildStdin>
+    writer: BufWriter<ChildStdin>,
+
+    /// The process id of the engine, to tell engines apart in the echo
+    pid: u32,
 }
 
 impl UciWriter {
-    pub fn new(stdin: ChildStdin) -> Self {
-        Self { writer: BufWriter::new(stdin) }
+    pub fn new(stdin: ChildStdin, pid: u32) -> Self {
+        Self { writer: BufWriter::new(stdin), pid }
     }
 
     pub fn write(&mut self, msg: UciClientMessage) -> anyhow::Result<()> {
-        self.writer.write(format!("{}\n", msg.to_string()).as_bytes())?;
+        self.write_line(&msg.to_string())
+    }
+
+    pub fn write_line(&mut self, line: &str) -> anyhow::Result<()> {
+        if verbosity() >= 1 {
+            eprintln!("[{}] > {}", self.pid, line.trim_end());
+        }
+
+        self.writer.write_all(format!("{}\n", line).as_bytes())?;
         self.writer.flush()?;
         Ok(())
     }
 }
 
+/// Reads the engine's output on a background thread, so we can stop waiting
+/// for an engine that's gone unresponsive.
 struct UciReader {
-    reader: BufReader<ChildStdout>
+    receiver: Receiver<String>,
+
+    /// Whether the engine has closed its output
+    closed: bool,
 }
 
 impl UciReader {
-    pub fn new(stdout: ChildStdout) -> Self {
-        Self { reader: BufReader::new(stdout) }
+    /// Start reading the output of the engine with the given process id
+    pub fn new(stdout: ChildStdout, pid: u32) -> Self {
+        let (sender, receiver) = mpsc::channel();
+
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let line = match line {
+                    Ok(line) => line,
+
+                    // A line that isn't valid UTF-8 has been read all the
+                    // same, so skip it rather than giving up on the engine
+                    Err(err) if err.kind() == ErrorKind::InvalidData => continue,
+                    Err(_) => break,
+                };
+
+                match verbosity() {
+                    1 if is_uci_message(&line) => eprintln!("[{pid}] < {line}"),
+                    2.. => eprintln!("[{pid}] < {line}"),
+                    _ => {},
+                }
+
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Self { receiver, closed: false }
+    }
+
+    /// Return the next line of output, waiting at most until the deadline 
+    /// (if any).
+    ///
+    /// Returns `Ok(None)` when the engine closed its output, and an error if 
+    /// the deadline passed before a line came in.
+    pub fn next_line_until(
+        &mut self, 
+        deadline: Option<Instant>
+    ) -> Result<Option<String>, RecvTimeoutError> {
+        let line = match deadline {
+            Some(deadline) => {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                self.receiver.recv_timeout(remaining)
+            },
+
+            None => self.receiver.recv()
+                .map_err(|_| RecvTimeoutError::Disconnected),
+        };
+
+        match line {
+            Ok(line) => Ok(Some(line)),
+            Err(RecvTimeoutError::Disconnected) => {
+                self.closed = true;
+                Ok(None)
+            },
+            Err(RecvTimeoutError::Timeout) => Err(RecvTimeoutError::Timeout),
+        }
+    }
+}
+
+/// A line of output from the engine
+enum EngineMessage {
+    Info(Info),
+    BestMove(String),
+    UciOk,
+    ReadyOk,
+
+    /// Any other output, which we don't need to act on
+    Other,
+}
+
+/// Info lines get parsed by us, rather than `simbelmyne_uci`, which panics on
+/// info lines it can't parse. Best moves are kept as the engine sent them, so
+/// `bestmove (none)` in a position without any legal moves ends the search 
+/// too.
+impl FromStr for EngineMessage {
+    type Err = anyhow::Error;
+
+    fn from_str(line: &str) -> anyhow::Result<Self> {
+        let mut words = line.split_whitespace();
+
+        let message = match words.next() {
+            Some("info") => Self::Info(line.parse()
+                .map_err(|_| anyhow!("invalid info line '{line}'"))?),
+
+            Some("bestmove") => Self::BestMove(words.next()
+                .ok_or_else(|| anyhow!("invalid bestmove line '{line}'"))?
+                .to_string()),
+
+            Some("uciok") => Self::UciOk,
+            Some("readyok") => Self::ReadyOk,
+            _ => Self::Other,
+        };
+
+        Ok(message)
     }
 }
 
-impl Iterator for UciReader {
-    type Item = UciEngineMessage;
+/// Parse a line of engine output. Lines that look like a message we act on,
+/// but can't be parsed, are reported with a warning rather than dropped 
+/// silently.
+fn parse_message(line: &str) -> EngineMessage {
+    line.parse().unwrap_or_else(|err| {
+        eprintln!("{} ignoring {err}", "Warning:".yellow());
+        EngineMessage::Other
+    })
+}
+
+/// Whether the line is one of the messages an engine can send according to 
+/// UCI, rather than any other output
+fn is_uci_message(line: &str) -> bool {
+    const MESSAGES: [&str; 8] = [
+        "id", "uciok", "readyok", "bestmove", "copyprotection", "registration", "info", "option",
+    ];
 
-    fn next(&mut self) -> Option<Self::Item> {
-        (&mut self.reader)
-            .lines()
-            .filter_map(|line| line.ok())
-            .filter_map(|line| line.parse().ok())
-            .next()
+    line.split_whitespace()
+        .next()
+        .is_some_and(|word| MESSAGES.contains(&word))
+}
+
+/// Copy the engine's stderr output to the log file on a background thread, 
+/// prefixing every line with the position that was being searched at the time.
+fn spawn_logger(stderr: ChildStderr, file: File, position: Arc<Mutex<String>>) {
+    thread::spawn(move || {
+        let mut file = BufWriter::new(file);
+
+        for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+            let fen = position.lock()
+                .map(|fen| fen.clone())
+                .unwrap_or_default();
+
+            if writeln!(file, "[{fen}] {line}").and_then(|_| file.flush()).is_err() {
+                break;
+            }
+        }
+    });
+}
+
+/// Extract the node count from a `Nodes searched: <n>` line
+fn parse_perft_nodes(line: &str) -> Option<u64> {
+    let (label, nodes) = line.split_once(':')?;
+
+    if !label.trim().eq_ignore_ascii_case("nodes searched") {
+        return None;
     }
+
+    nodes.trim().parse().ok()
+}
+
+/// Extract the option name from an `option name <name> type <type> ...` line
+fn parse_option_name(line: &str) -> Option<String> {
+    let rest = line.trim().strip_prefix("option name ")?;
+    let name = rest.split_once(" type ").map_or(rest, |(name, _)| name);
+
+    Some(name.trim().to_string())
 }
 
+////////////////////////////////////////////////////////////////////////////////
+///
+/// Tests
+///
+////////////////////////////////////////////////////////////////////////////////
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::diff::Score;
+
+    const STARTPOS: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+    /// Start one of the fake engines in `tests/engines`
+    fn fake_engine(name: &str) -> Engine {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/engines").join(name);
+        Engine::new(&path, &[], None, None).unwrap()
+    }
+
+    #[test]
+    fn perft_counts_of_the_start_position() {
+        let mut engine = fake_engine("perft.sh");
+        let board: Board = STARTPOS.parse().unwrap();
+
+        for (depth, expected) in [(1, 20), (2, 400), (3, 8902), (4, 197281)] {
+            let nodes = engine.perft(board, &format!("go perft {depth}"), None).unwrap();
+            assert_eq!(nodes, expected, "perft {depth}");
+        }
+
+        let kiwipete = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        assert_eq!(engine.perft(kiwipete.parse().unwrap(), "go perft 1", None).unwrap(), 0);
+    }
+
+    #[test]
+    fn unparseable_lines_are_reported() {
+        let line = "info depth two nodes lots";
+        let err = line.parse::<EngineMessage>().err().unwrap();
+
+        assert!(err.to_string().contains(line), "{err}");
+        assert!(matches!(parse_message(line), EngineMessage::Other));
+
+        // The rest of the search output still counts
+        let mut engine = fake_engine("garbled.sh");
+        let result = engine.search(STARTPOS.parse().unwrap(), TimeControl::Depth(2), &[], None).unwrap();
+
+        assert_eq!(result.nodes.0, 400);
+        assert_eq!(result.best_move, "d2d4");
+    }
+
+    #[test]
+    fn scoreless_updates_keep_the_secondary_line() {
+        let mut engine = fake_engine("multipv.sh");
+        let result = engine.search(STARTPOS.parse().unwrap(), TimeControl::Depth(2), &[], None).unwrap();
+
+        assert_eq!(result.lines.len(), 1);
+        assert_eq!(result.lines[0].score, Score::Cp(15));
+        assert_eq!(result.lines[0].pv, ["d2d4", "d7d5"]);
+    }
+
+    #[test]
+    fn stalled_search_times_out() {
+        let mut engine = fake_engine("stall.sh");
+        let timeout = Duration::from_millis(200);
+        let start = Instant::now();
+
+        let err = engine.search(STARTPOS.parse().unwrap(), TimeControl::Depth(10), &[], Some(timeout))
+            .err()
+            .unwrap();
+
+        assert!(err.to_string().contains("timed out after 200ms"), "{err}");
+        assert!(start.elapsed() >= timeout);
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn waits_for_a_slow_readyok() {
+        let mut engine = fake_engine("slow_readyok.sh");
+        let delay = Duration::from_millis(300);
+
+        assert!(engine.startup().readyok >= delay);
+        assert!(engine.startup().readyok >= engine.startup().uciok + delay);
+
+        // Every new game waits for the engine again before searching
+        let start = Instant::now();
+        let result = engine.search(STARTPOS.parse().unwrap(), TimeControl::Depth(1), &[], None).unwrap();
+
+        assert!(start.elapsed() >= delay);
+        assert_eq!(result.best_move, "e2e4");
+    }
+
+    #[test]
+    fn dropping_the_engine_ends_its_process() {
+        let is_running = |pid: u32| Command::new("kill")
+            .args(["-0", &pid.to_string()])
+            .stderr(Stdio::null())
+            .status()
+            .unwrap()
+            .success();
+
+        // One that quits when asked, and one that has to be killed
+        for name in ["perft.sh", "stall.sh"] {
+            let engine = fake_engine(name);
+            let pid = engine.process.id();
+
+            assert!(is_running(pid), "{name}");
+            drop(engine);
+            assert!(!is_running(pid), "{name}");
+        }
+    }
+}
@@ -0,0 +1,394 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+use anyhow::anyhow;
+use simbelmyne_chess::board::Board;
+use simbelmyne_chess::movegen::moves::Move;
+
+/// A single line of an EPD test suite: a position, followed by a list of
+/// `opcode operand;` operations, e.g.,
+///
+/// `r1b1kb1r/3q1ppp/pBp1pn2/8/Np3P2/5B2/PPP3PP/R2Q1RK1 w kq - bm Bxc6; id "WAC.002";`
+///
+/// Plain FENs are accepted as well, and simply have no operations.
+#[derive(Debug, Clone, Default)]
+pub struct Epd {
+    /// The position as a full FEN, including the move counters
+    pub fen: String,
+
+    /// The operations, keyed by opcode. Quoted operands are unquoted.
+    pub operations: HashMap<String, String>,
+}
+
+impl Epd {
+    /// Wrap a plain FEN that doesn't carry any operations. Runs of whitespace
+    /// are collapsed, since `Board::from_fen` expects single spaces.
+    pub fn from_fen(fen: &str) -> Self {
+        let fen = fen.split_whitespace().collect::<Vec<_>>().join(" ");
+        Self { fen, operations: HashMap::new() }
+    }
+
+    /// Parse a line of a plain suite file: a FEN, optionally preceded by a 
+    /// label, as in `label;fen`. The label is stored as the `id` operation.
+    pub fn from_line(line: &str) -> Self {
+        let Some((label, fen)) = line.split_once(';') else {
+            return Self::from_fen(line);
+        };
+
+        let mut epd = Self::from_fen(fen);
+        epd.operations.insert(String::from("id"), label.trim().to_string());
+
+        epd
+    }
+
+    /// The `id` operation, if any. Used as the label of the position.
+    pub fn id(&self) -> Option<String> {
+        self.operations.get("id").cloned()
+    }
+
+    /// The moves listed in the `searchmoves` operation, in UCI notation. The
+    /// engine only considers these moves at the root.
+    pub fn search_moves(&self) -> Vec<String> {
+        self.operations.get("searchmoves")
+            .map(|moves| moves.split_whitespace().map(String::from).collect())
+            .unwrap_or_default()
+    }
+
+    /// The moves listed in the `bm` operation, in SAN. Use [`san_to_uci`] to
+    /// compare them against the moves reported by an engine.
+    pub fn best_moves(&self) -> Vec<String> {
+        self.operations.get("bm")
+            .map(|moves| moves.split_whitespace().map(String::from).collect())
+            .unwrap_or_default()
+    }
+}
+
+impl FromStr for Epd {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let mut rest = s;
+        let mut position = Vec::new();
+
+        // Pieces, side to move, castling rights and en-passant square
+        for _ in 0..4 {
+            let (field, tail) = next_field(rest)
+                .ok_or_else(|| anyhow!("Invalid EPD '{s}', expected at least 4 fields"))?;
+
+            position.push(field);
+            rest = tail;
+        }
+
+        // Some suites include the move counters, as in a regular FEN
+        let mut counters = None;
+
+        if let Some((half_moves, tail)) = next_field(rest) {
+            if let Some((full_moves, tail)) = next_field(tail) {
+                if half_moves.parse::<u32>().is_ok() && full_moves.parse::<u32>().is_ok() {
+                    counters = Some((half_moves, full_moves));
+                    rest = tail;
+                }
+            }
+        }
+
+        let operations = parse_operations(rest);
+
+        // Otherwise, fall back to the `hmvc` and `fmvn` operations
+        let (half_moves, full_moves) = counters.unwrap_or_else(|| (
+            operations.get("hmvc").map_or("0", String::as_str),
+            operations.get("fmvn").map_or("1", String::as_str),
+        ));
+
+        let fen = format!("{} {half_moves} {full_moves}", position.join(" "));
+
+        Ok(Self { fen, operations })
+    }
+}
+
+/// Split off the next whitespace-separated field
+fn next_field(s: &str) -> Option<(&str, &str)> {
+    let s = s.trim_start();
+
+    if s.is_empty() {
+        return None;
+    }
+
+    Some(s.split_once(char::is_whitespace).unwrap_or((s, "")))
+}
+
+/// Parse a list of `opcode operand;` operations. Semicolons inside quoted
+/// operands don't end the operation.
+fn parse_operations(s: &str) -> HashMap<String, String> {
+    let mut operations = HashMap::new();
+    let mut current = String::new();
+    let mut quoted = false;
+
+    for c in s.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                current.push(c);
+            },
+
+            ';' if !quoted => {
+                insert_operation(&mut operations, &current);
+                current.clear();
+            },
+
+            _ => current.push(c),
+        }
+    }
+
+    insert_operation(&mut operations, &current);
+
+    operations
+}
+
+/// Split a single operation into its opcode and operand, and add it to the map
+fn insert_operation(operations: &mut HashMap<String, String>, operation: &str) {
+    let operation = operation.trim();
+
+    if operation.is_empty() {
+        return;
+    }
+
+    let (opcode, operand) = operation.split_once(char::is_whitespace)
+        .unwrap_or((operation, ""));
+
+    let operand = operand.trim();
+    let operand = operand.strip_prefix('"')
+        .and_then(|operand| operand.strip_suffix('"'))
+        .unwrap_or(operand);
+
+    operations.insert(opcode.to_string(), operand.to_string());
+}
+
+/// Convert a move in SAN (e.g., `Nbxd7+`) to the UCI notation engines report 
+/// (e.g., `b8d7`), by finding the matching legal move on the board.
+pub fn san_to_uci(board: &Board, san: &str) -> Option<String> {
+    san_to_move(board, san).map(|mv| mv.to_string().to_lowercase())
+}
+
+/// Find the legal move on the board that a move in SAN refers to
+pub fn san_to_move(board: &Board, san: &str) -> Option<Move> {
+    let san = san.trim_end_matches(['+', '#', '!', '?']);
+
+    // Castling is the only move that doesn't mention the target square
+    let castle_file = match san {
+        "O-O" | "0-0" => Some('g'),
+        "O-O-O" | "0-0-0" => Some('c'),
+        _ => None,
+    };
+
+    let (san, promotion) = match san.split_once('=') {
+        Some((san, promo)) => (san, promo.chars().next()),
+        None => match san.chars().last() {
+            Some(c @ ('Q' | 'R' | 'B' | 'N')) => (&san[..san.len() - 1], Some(c)),
+            _ => (san, None),
+        },
+    };
+
+    let (piece, san) = match san.chars().next() {
+        Some(c @ ('K' | 'Q' | 'R' | 'B' | 'N')) => (c, &san[1..]),
+        _ => ('P', san),
+    };
+
+    let san = san.replace('x', "");
+    let target = san.get(san.len().checked_sub(2)?..)?;
+    let disambiguation = &san[..san.len() - 2];
+
+    board.legal_moves::<true>().into_iter()
+        .map(|mv| (mv, mv.to_string().to_lowercase()))
+        .find(|(mv, uci)| {
+            let moved = board.get_at(mv.src())
+                .map(|piece| piece.to_string().to_ascii_uppercase());
+
+            if let Some(file) = castle_file {
+                return mv.is_castle() && uci[2..].starts_with(file);
+            }
+
+            moved.as_deref() == Some(&piece.to_string())
+                && &uci[2..4] == target
+                && disambiguation.chars().all(|c| uci[..2].contains(c))
+                && uci[4..].chars().next() == promotion.map(|c| c.to_ascii_lowercase())
+        })
+        .map(|(mv, _)| mv)
+}
+
+/// Parse a FEN into a board, after checking the piece placement. 
+///
+/// `Board::from_fen` assumes the placement is well-formed, and panics on, e.g.,
+/// a rank that's too long or a missing king.
+pub fn parse_fen(fen: &str) -> anyhow::Result<Board> {
+    let placement = fen.split_whitespace().next().unwrap_or_default();
+    let ranks = placement.split('/').collect::<Vec<_>>();
+
+    if ranks.len() != 8 {
+        return Err(anyhow!("expected 8 ranks, found {}", ranks.len()));
+    }
+
+    for rank in &ranks {
+        let mut squares = 0;
+
+        for c in rank.chars() {
+            squares += match c {
+                '1'..='8' => c.to_digit(10).unwrap_or_default(),
+                'p' | 'n' | 'b' | 'r' | 'q' | 'k' 
+                | 'P' | 'N' | 'B' | 'R' | 'Q' | 'K' => 1,
+                _ => return Err(anyhow!("invalid character '{c}' in rank '{rank}'")),
+            };
+        }
+
+        if squares != 8 {
+            return Err(anyhow!("rank '{rank}' has {squares} squares instead of 8"));
+        }
+    }
+
+    for king in ['K', 'k'] {
+        let count = placement.chars().filter(|&c| c == king).count();
+
+        if count != 1 {
+            return Err(anyhow!("expected exactly one '{king}', found {count}"));
+        }
+    }
+
+    let castling = fen.split_whitespace().nth(2).unwrap_or_default();
+
+    if castling.chars().any(is_rook_file) {
+        return Err(anyhow!(
+            "castling rights '{castling}' name the rook files, as in Chess960. Pass --chess960 to accept them"
+        ));
+    }
+
+    fen.parse()
+}
+
+/// Rewrite the castling rights of a Chess960 FEN from Shredder-FEN, which 
+/// names the file of every castling rook (as in `HAha`), to X-FEN, which uses
+/// `KQkq` like regular chess. `Board` only parses the latter, and engines 
+/// accept either in Chess960 mode.
+///
+/// X-FEN can only leave out the file when the castling rook is the outermost
+/// rook on its side of the king, so any other castling rights are rejected.
+/// FENs without any files in their castling rights are returned as is.
+pub fn to_xfen(fen: &str) -> anyhow::Result<String> {
+    let mut fields = fen.split(' ').collect::<Vec<_>>();
+    let castling = fields.get(2).copied().unwrap_or_default();
+
+    if !castling.chars().any(is_rook_file) {
+        return Ok(fen.to_string());
+    }
+
+    let ranks = fields[0].split('/').collect::<Vec<_>>();
+
+    // Leave reporting a malformed placement up to `parse_fen`
+    let (Some(black), Some(white)) = (ranks.first(), ranks.get(7)) else {
+        return Ok(fen.to_string());
+    };
+
+    let mut rights = String::new();
+
+    for right in castling.chars() {
+        if !is_rook_file(right) {
+            rights.push(right);
+            continue;
+        }
+
+        let (back_rank, king, rook) = if right.is_ascii_uppercase() {
+            (expand_rank(white), 'K', 'R')
+        } else {
+            (expand_rank(black), 'k', 'r')
+        };
+
+        let file = (right.to_ascii_lowercase() as u8 - b'a') as usize;
+        let king_file = back_rank.iter().position(|&piece| piece == king);
+
+        let Some(king_file) = king_file.filter(|_| back_rank.get(file) == Some(&rook)) else {
+            return Err(anyhow!("castling right '{right}' has no king and rook on the back rank"));
+        };
+
+        // Any rook further out on the same side makes the X-FEN ambiguous
+        let (side, outside) = if file > king_file {
+            ('K', &back_rank[file + 1..])
+        } else {
+            ('Q', &back_rank[..file])
+        };
+
+        if outside.contains(&rook) {
+            return Err(anyhow!(
+                "castling right '{right}' is for an inner rook, which isn't supported"
+            ));
+        }
+
+        rights.push(if king == 'K' { side } else { side.to_ascii_lowercase() });
+    }
+
+    let mut rights = rights.chars().collect::<Vec<_>>();
+    rights.sort_by_key(|&right| "KQkq".find(right));
+    rights.dedup();
+
+    let rights = rights.into_iter().collect::<String>();
+    fields[2] = &rights;
+
+    Ok(fields.join(" "))
+}
+
+/// Whether a character in the castling rights names the file of a rook, as
+/// in Shredder-FEN
+fn is_rook_file(c: char) -> bool {
+    matches!(c.to_ascii_lowercase(), 'a'..='h')
+}
+
+/// Expand a rank of a FEN placement into its 8 squares, with empty squares 
+/// as spaces
+fn expand_rank(rank: &str) -> Vec<char> {
+    rank.chars()
+        .flat_map(|c| match c.to_digit(10) {
+            Some(gap) => vec![' '; gap as usize],
+            None => vec![c],
+        })
+        .collect()
+}
+
+////////////////////////////////////////////////////////////////////////////////
+///
+/// Tests
+///
+////////////////////////////////////////////////////////////////////////////////
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shredder_fen_becomes_xfen() {
+        // The first of the Chess960 perft positions, with both rooks still 
+        // on either side of the king
+        let fen = "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9";
+        let xfen = to_xfen(fen).unwrap();
+
+        assert_eq!(xfen, "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w KQkq - 2 9");
+        assert!(parse_fen(&xfen).is_ok());
+
+        // Already in X-FEN
+        assert_eq!(to_xfen(&xfen).unwrap(), xfen);
+    }
+
+    #[test]
+    fn chess960_start_position_becomes_xfen() {
+        let fen = "bqnb1rkr/pppppppp/8/8/8/8/PPPPPPPP/BQNB1RKR w HFhf - 0 1";
+        let xfen = to_xfen(fen).unwrap();
+
+        assert_eq!(xfen, "bqnb1rkr/pppppppp/8/8/8/8/PPPPPPPP/BQNB1RKR w KQkq - 0 1");
+        assert!(parse_fen(&xfen).is_ok());
+    }
+
+    #[test]
+    fn inner_rook_castling_is_rejected() {
+        // Castling with the b-rook, while the a-rook sits outside of it
+        let err = to_xfen("rr2k3/8/8/8/8/8/8/RR2K3 w Bb - 0 1").unwrap_err();
+        assert!(err.to_string().contains("inner rook"), "{err}");
+
+        // The outer rook is fine
+        let xfen = to_xfen("rr2k3/8/8/8/8/8/8/RR2K3 w Aa - 0 1").unwrap();
+        assert_eq!(xfen, "rr2k3/8/8/8/8/8/8/RR2K3 w Qq - 0 1");
+    }
+}
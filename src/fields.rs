@@ -1,40 +1,115 @@
+use std::fmt::Display;
+
+use clap::ValueEnum;
+
 use crate::Cli;
 
 pub struct Fields {
+    /// Whether to show the label of the position. Only shown when the suite
+    /// has labels, so it's not controlled by a flag.
+    pub label: bool,
+
+    /// Whether to show the search depth. Only shown when searching multiple
+    /// depths, so it's not controlled by a flag.
+    pub depth: bool,
+
     pub nodes: bool,
     pub time: bool,
     pub nps: bool,
     pub branching: bool,
+    pub ebf: bool,
+    pub reached_depth: bool,
+    pub seldepth: bool,
+    pub hashfull: bool,
+    pub tbhits: bool,
+
+    /// Whether to show the time it took to reach the target depth. Only
+    /// shown when passing `--target-depth`.
+    pub time_to_depth: bool,
+
     pub score: bool,
-    pub best_move: bool
+    pub best_move: bool,
+
+    /// Whether the best move matches the EPD `bm` operation. Only available
+    /// when running an EPD suite, so it's not controlled by a flag.
+    pub solved: bool,
+
+    pub pv: bool,
+
+    /// Whether to print node counts and times in a human-readable form
+    pub human: bool,
 }
 
 impl Default for Fields {
     fn default() -> Self {
         Self {
+            label: true,
+            depth: true,
             nodes: true,
             time: true,
             nps: true,
             branching: true,
+            ebf: true,
+            reached_depth: true,
+            seldepth: true,
+            hashfull: true,
+            tbhits: true,
+            time_to_depth: true,
             score: true,
-            best_move: true
+            best_move: true,
+            solved: true,
+            pv: true,
+            human: false,
         }
     }
 }
 
 pub trait Extract {
-    fn extract<'a>(&self, fields: &'a Fields) -> Vec<String>;
+    fn extract(&self, fields: &Fields) -> Vec<String>;
 }
 
-impl<'a> From<&Cli> for Fields {
+impl From<&Cli> for Fields {
     fn from(value: &Cli) -> Self {
         Self {
+            label: false,
+            depth: false,
             nodes: value.all || value.nodes,
             time: value.all || value.time,
             nps: value.all || value.nps,
             branching: value.all || value.branching,
+            ebf: value.all || value.ebf,
+            reached_depth: value.all || value.reached_depth,
+            seldepth: value.all || value.seldepth,
+            hashfull: value.all || value.hashfull,
+            tbhits: value.all || value.tbhits,
+            time_to_depth: value.target_depth.is_some(),
             score: value.all || value.score,
             best_move: value.all || value.best_move,
+            solved: false,
+            pv: value.all || value.pv,
+            human: value.human,
         }
     }
 }
+
+impl Fields {
+    /// Format a metric for display, using its human-readable (alternate) 
+    /// form when requested
+    pub fn format(&self, value: &impl Display) -> String {
+        if self.human {
+            format!("{value:#}")
+        } else {
+            value.to_string()
+        }
+    }
+}
+
+/// A metric to sort the rows of the output by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SortKey {
+    Nodes,
+    Time,
+    Nps,
+    Branching,
+    Score,
+}
@@ -0,0 +1,189 @@
+use std::path::Path;
+
+use anyhow::anyhow;
+use rusqlite::{params, Connection};
+
+use crate::diff::{Nodes, Nps, Score, Time};
+use crate::search_result::SearchResult;
+use crate::snapshot::Metadata;
+
+/// The tables runs are recorded in. Every statement is idempotent, so this is
+/// run every time the database is opened.
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS runs (
+        id INTEGER PRIMARY KEY,
+        timestamp INTEGER,
+        git_commit TEXT,
+        engine TEXT,
+        meta TEXT NOT NULL
+    );
+
+    CREATE TABLE IF NOT EXISTS results (
+        run_id INTEGER NOT NULL REFERENCES runs(id),
+        position TEXT NOT NULL,
+        label TEXT,
+        depth INTEGER NOT NULL,
+        nodes INTEGER NOT NULL,
+        time INTEGER NOT NULL,
+        nps INTEGER,
+        score_cp INTEGER,
+        score_mate INTEGER,
+        best_move TEXT NOT NULL
+    );
+
+    CREATE INDEX IF NOT EXISTS results_by_run ON results(run_id);
+    CREATE INDEX IF NOT EXISTS results_by_position ON results(position);
+";
+
+////////////////////////////////////////////////////////////////////////////////
+///
+/// History
+///
+////////////////////////////////////////////////////////////////////////////////
+/// A SQLite database that every run can be appended to, to track the metrics
+/// of an engine over many commits
+pub struct History {
+    conn: Connection,
+}
+
+impl History {
+    /// Open the database, creating it (and its tables) if it doesn't exist yet
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let conn = Connection::open(path)
+            .map_err(|err| anyhow!("Failed to open database {}: {err}", path.display()))?;
+
+        conn.execute_batch(SCHEMA)
+            .map_err(|err| anyhow!("Failed to set up database {}: {err}", path.display()))?;
+
+        Ok(Self { conn })
+    }
+
+    /// Record a run, along with the settings it was run with, and the commit
+    /// the engine was built from. Returns the id of the new run.
+    pub fn record(
+        &self,
+        meta: &Metadata,
+        commit: Option<&str>,
+        results: &[SearchResult]
+    ) -> anyhow::Result<i64> {
+        // Either the whole run goes in, or none of it does
+        let tx = self.conn.unchecked_transaction()?;
+
+        tx.execute(
+            "INSERT INTO runs (timestamp, git_commit, engine, meta) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                meta.timestamp,
+                commit,
+                meta.engine.as_ref().map(ToString::to_string),
+                serde_json::to_string(meta)?,
+            ],
+        )?;
+
+        let run = tx.last_insert_rowid();
+
+        {
+            let mut insert = tx.prepare(
+                "INSERT INTO results
+                    (run_id, position, label, depth, nodes, time, nps, score_cp, score_mate, best_move)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)"
+            )?;
+
+            for result in results {
+                let (cp, mate) = match result.score {
+                    Score::Cp(cp) => (Some(cp), None),
+                    Score::Mate { mate } => (None, Some(mate)),
+                };
+
+                insert.execute(params![
+                    run,
+                    result.position,
+                    result.id,
+                    result.depth,
+                    result.nodes.0,
+                    result.time.0,
+                    result.nps.0,
+                    cp,
+                    mate,
+                    result.best_move,
+                ])?;
+            }
+        }
+
+        tx.commit()?;
+
+        Ok(run)
+    }
+
+    /// The average metrics of every recorded run, oldest first. When a
+    /// position is given (as its FEN or its label), only its results count,
+    /// and runs that didn't search it are left out.
+    pub fn trend(&self, position: Option<&str>) -> anyhow::Result<Vec<RunSummary>> {
+        let mut query = self.conn.prepare(
+            "SELECT runs.id, runs.timestamp, runs.git_commit, runs.engine,
+                COUNT(*), AVG(results.nodes), AVG(results.time), AVG(results.nps)
+            FROM runs JOIN results ON results.run_id = runs.id
+            WHERE ?1 IS NULL OR results.position = ?1 OR results.label = ?1
+            GROUP BY runs.id
+            ORDER BY runs.id"
+        )?;
+
+        let runs = query.query_map(params![position], |row| Ok(RunSummary {
+            id: row.get(0)?,
+            timestamp: row.get(1)?,
+            commit: row.get(2)?,
+            engine: row.get(3)?,
+            positions: row.get(4)?,
+            nodes: Nodes(row.get::<_, f64>(5)?.round() as u64),
+            time: Time(row.get::<_, f64>(6)?.round() as u64),
+            nps: Nps(row.get::<_, Option<f64>>(7)?.map(|nps| nps.round() as u64)),
+        }))?;
+
+        Ok(runs.collect::<Result<Vec<_>, _>>()?)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+///
+/// Run summary
+///
+////////////////////////////////////////////////////////////////////////////////
+/// The metrics of a recorded run, averaged over its positions
+pub struct RunSummary {
+    pub id: i64,
+
+    /// When the run finished, in seconds since the Unix epoch
+    pub timestamp: Option<u64>,
+
+    /// The commit the engine was built from, as passed with `--commit`
+    pub commit: Option<String>,
+
+    /// The engine, as it identified itself
+    pub engine: Option<String>,
+
+    /// The number of searches that were averaged over
+    pub positions: usize,
+
+    pub nodes: Nodes,
+    pub time: Time,
+    pub nps: Nps,
+}
+
+/// Format a Unix timestamp as a UTC date and time, e.g., `2024-05-01 14:03`
+pub fn format_timestamp(timestamp: u64) -> String {
+    let days = (timestamp / 86_400) as i64;
+    let minutes = timestamp % 86_400 / 60;
+
+    // Convert the days since the epoch to a civil date, following Howard
+    // Hinnant's `civil_from_days`
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!("{year}-{month:02}-{day:02} {:02}:{:02}", minutes / 60, minutes % 60)
+}
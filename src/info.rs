@@ -0,0 +1,89 @@
+use std::str::{FromStr, SplitWhitespace};
+use anyhow::anyhow;
+
+use crate::diff::Score;
+
+/// The search information reported by the engine in an `info` line.
+///
+/// We parse these ourselves rather than going through `simbelmyne_uci`, since
+/// its parser skips over several fields we care about (the pv, mate scores,
+/// ...).
+#[derive(Debug, Default, Clone)]
+pub struct Info {
+    pub depth: Option<usize>,
+    pub seldepth: Option<usize>,
+    pub time: Option<u64>,
+    pub nodes: Option<u64>,
+
+    /// How full the hash table is, in permille
+    pub hashfull: Option<u32>,
+
+    /// The number of tablebase hits
+    pub tbhits: Option<u64>,
+
+    pub score: Option<Score>,
+    pub pv: Vec<String>,
+
+    /// Which line this is, when searching multiple PVs
+    pub multipv: Option<usize>,
+
+    /// Free-form text sent with `info string`
+    pub string: Option<String>,
+}
+
+impl FromStr for Info {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let mut parts = s.split_whitespace();
+
+        if parts.next() != Some("info") {
+            return Err(anyhow!("Not an info line: {s}"));
+        }
+
+        let mut info = Info::default();
+
+        while let Some(token) = parts.next() {
+            match token {
+                "depth" => info.depth = Some(next_value(&mut parts, s)?),
+                "seldepth" => info.seldepth = Some(next_value(&mut parts, s)?),
+                "time" => info.time = Some(next_value(&mut parts, s)?),
+                "nodes" => info.nodes = Some(next_value(&mut parts, s)?),
+                "multipv" => info.multipv = Some(next_value(&mut parts, s)?),
+                "hashfull" => info.hashfull = Some(next_value(&mut parts, s)?),
+                "tbhits" => info.tbhits = Some(next_value(&mut parts, s)?),
+
+                "score" => {
+                    let kind = parts.next();
+                    let value = next_value(&mut parts, s)?;
+
+                    info.score = match kind {
+                        Some("cp") => Some(Score::Cp(value)),
+                        Some("mate") => Some(Score::Mate { mate: value }),
+                        _ => Err(anyhow!("Not a valid info line: {s}. Failed to parse 'score'."))?
+                    };
+                },
+
+                // The pv is always the last field on the line
+                "pv" => info.pv = parts.by_ref().map(String::from).collect(),
+
+                // The rest of the line is free-form text
+                "string" => {
+                    info.string = Some(parts.by_ref().collect::<Vec<_>>().join(" "));
+                },
+
+                // Skip anything we don't recognize
+                _ => continue,
+            }
+        }
+
+        Ok(info)
+    }
+}
+
+/// Parse the next token on the line as a value
+fn next_value<T: FromStr>(parts: &mut SplitWhitespace, line: &str) -> anyhow::Result<T> {
+    parts.next()
+        .and_then(|value| value.parse().ok())
+        .ok_or_else(|| anyhow!("Not a valid info line: {line}"))
+}
@@ -0,0 +1,25 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Whether the run was interrupted with Ctrl-C
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// The exit code of a process that got interrupted (128 + SIGINT)
+pub const EXIT_CODE: i32 = 130;
+
+/// Catch Ctrl-C, so the run can wind down: the searches in progress are
+/// stopped, no new ones are started, and the results so far are reported
+/// (and saved, when requested). A second Ctrl-C exits right away.
+pub fn install() -> anyhow::Result<()> {
+    ctrlc::set_handler(|| {
+        if INTERRUPTED.swap(true, Ordering::SeqCst) {
+            std::process::exit(EXIT_CODE);
+        }
+    })?;
+
+    Ok(())
+}
+
+/// Whether the run was interrupted, and should stop searching
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
@@ -0,0 +1,111 @@
+use crate::diff::GateMetric;
+use crate::search_result::SearchResult;
+
+////////////////////////////////////////////////////////////////////////////////
+///
+/// JUnit
+///
+////////////////////////////////////////////////////////////////////////////////
+/// Render the results as a JUnit XML report, with a test case per position,
+/// named after its EPD `id` (or its FEN when it doesn't have one).
+///
+/// A position fails when the engine missed the best move the suite expects,
+/// or when its gate metric regressed by more than the threshold. The
+/// regressions are percentages, in the same order as the results. Positions
+/// the engine crashed or timed out on are reported as errors.
+pub fn to_junit(
+    results: &[SearchResult],
+    regressions: &[Option<f32>],
+    metric: GateMetric,
+    threshold: Option<f32>,
+    crashed: &[String],
+) -> String {
+    let depths = results.iter().any(|result| result.depth != results[0].depth);
+    let mut cases = String::new();
+    let mut failures = 0;
+
+    for (result, regression) in results.iter().zip(regressions) {
+        let mut name = result.id.clone().unwrap_or_else(|| result.position.clone());
+
+        if depths {
+            name.push_str(&format!(" (depth {})", result.depth));
+        }
+
+        let mut messages = Vec::new();
+
+        if result.solved() == Some(false) {
+            messages.push(format!(
+                "Expected {}, but the engine played {}",
+                result.bm.join(" or "),
+                result.best_move
+            ));
+        }
+
+        if let (Some(threshold), Some(regression)) = (threshold, regression) {
+            if *regression > threshold {
+                messages.push(format!(
+                    "The {metric} regressed by {regression:.2}%, more than the allowed {threshold}%"
+                ));
+            }
+        }
+
+        cases.push_str(&format!(
+            "    <testcase name=\"{}\" classname=\"chess-bench\" time=\"{:.3}\">\n",
+            escape(&name),
+            result.time.0 as f64 / 1000.0,
+        ));
+
+        if !messages.is_empty() {
+            failures += 1;
+            let message = messages.join("; ");
+
+            cases.push_str(&format!(
+                "      <failure message=\"{}\">{}</failure>\n",
+                escape(&message),
+                escape(&result.position),
+            ));
+        }
+
+        cases.push_str("    </testcase>\n");
+    }
+
+    for fen in crashed {
+        cases.push_str(&format!(
+            "    <testcase name=\"{}\" classname=\"chess-bench\">\n",
+            escape(fen)
+        ));
+        cases.push_str("      <error message=\"The engine crashed or timed out\"/>\n");
+        cases.push_str("    </testcase>\n");
+    }
+
+    let time = results.iter().map(|result| result.time.0).sum::<u64>() as f64 / 1000.0;
+    let tests = results.len() + crashed.len();
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+
+    xml.push_str(&format!(
+        "<testsuites tests=\"{tests}\" failures=\"{failures}\" errors=\"{}\" time=\"{time:.3}\">\n",
+        crashed.len()
+    ));
+
+    xml.push_str(&format!(
+        "  <testsuite name=\"chess-bench\" tests=\"{tests}\" failures=\"{failures}\" errors=\"{}\" time=\"{time:.3}\">\n",
+        crashed.len()
+    ));
+
+    xml.push_str(&cases);
+    xml.push_str("  </testsuite>\n");
+    xml.push_str("</testsuites>\n");
+
+    xml
+}
+
+/// Escape the characters that have a special meaning in XML
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
@@ -1,18 +1,42 @@
-use std::io::BufReader;
-use std::path::PathBuf;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
 
-use clap::Parser;
-use diff::Diff;
-use engine::Engine;
+use anyhow::anyhow;
+use clap::{Parser, Subcommand};
+use colored::{Color, Colorize};
+use diff::{BFactorDiff, Diff, GateMetric, NpsDiff, TimeToDepthDiff, Worse, WorseIs};
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use diff::Time;
+use engine::{Engine, EngineOption, SearchTimeout};
+use epd::{parse_fen, to_xfen, Epd};
+#[cfg(feature = "history")]
+use history::History;
+use info::Info;
 use positions::POSITIONS;
+use report::{ColorChoice, Format, Report};
 use search_result::SearchResult;
-use tabulator::Tabulator;
+use simbelmyne_chess::board::Board;
+use simbelmyne_uci::time_control::TimeControl;
+use snapshot::{Metadata, Snapshot};
+use stats::{Aggregate, OutlierRule};
+use sweep::OptionSweep;
+use tabulator::{Alignment, Tabulator};
 
-use std::fs::File;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::Display;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use std::fs::write;
 
 use crate::fields::Extract;
 use crate::fields::Fields;
+use crate::fields::SortKey;
 
 mod positions;
 mod search_result;
@@ -21,219 +45,3433 @@ mod report;
 mod engine;
 mod tabulator;
 mod fields;
+mod info;
+mod snapshot;
+mod sweep;
+mod epd;
+mod pgn;
+#[cfg(feature = "history")]
+mod history;
+mod stats;
+mod config;
+mod junit;
+mod interrupt;
+
+/// The depth to search every position to, when `--depth` isn't passed
+const DEFAULT_DEPTH: usize = 10;
+
+/// The snapshot that's compared against, and saved to, by default
+const DEFAULT_SNAPSHOT: &str = "./bench_snapshot.json";
 
 /// Simple program to greet a person
 #[derive(Parser, Debug)]
-#[command(version, author, about)]
+#[command(version, author, about, subcommand_negates_reqs = true)]
 pub struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// The location of the engine binary
-    engine: PathBuf,
+    #[arg(required_unless_present = "engines")]
+    engine: Option<PathBuf>,
+
+    /// Another engine to benchmark side by side with the first. Repeat the
+    /// flag to add more engines. Every metric then gets a column per engine.
+    #[arg(long = "engine", id = "engines", value_name = "PATH", global = true)]
+    engines: Vec<PathBuf>,
+
+    /// A TOML file with default values for any of the flags, keyed by their
+    /// long name. Flags passed on the command line take precedence. Uses
+    /// `./chess-bench.toml` when it exists.
+    #[arg(long, value_name = "PATH", global = true)]
+    config: Option<PathBuf>,
+
+    /// The depth to which to search each position (10 by default). Pass a 
+    /// comma-separated list (e.g., `6,8,10`) to search every position at each
+    /// of the depths. When comparing against a snapshot, the depths the 
+    /// snapshot was taken at are used instead, unless `--re-depth` is passed.
+    #[arg(short, long, value_delimiter = ',', global = true)]
+    depth: Vec<usize>,
+
+    /// When comparing against a snapshot, search the positions at the depths
+    /// passed with `--depth`, rather than the ones the snapshot was taken at.
+    /// The verdict notes that the depths differ.
+    #[arg(long, requires = "depth", global = true)]
+    re_depth: bool,
+
+    /// Search each position for a fixed number of milliseconds instead of to a
+    /// fixed depth. The depth the engine reached is recorded in the snapshot.
+    #[arg(long, value_name = "MS", conflicts_with = "depth", global = true)]
+    movetime: Option<u64>,
 
-    /// The depth to which to search each position. Ignored when comparing 
-    /// diffs
-    #[arg(short, long, default_value = "10")]
-    depth: usize,
+    /// Stop searching each position after this many nodes, instead of at a 
+    /// fixed depth. The depth the engine reached is recorded in the snapshot.
+    #[arg(long, value_name = "N", conflicts_with_all = ["depth", "movetime"], global = true)]
+    max_nodes: Option<usize>,
+
+    /// Search every position this many times, and report the average, along
+    /// with the standard deviation of the time and nps
+    #[arg(long, default_value = "1", value_parser = clap::value_parser!(u32).range(1..), global = true)]
+    repeat: u32,
+
+    /// Search every position this many times before the measured searches,
+    /// and throw away the results. Warmup searches don't count towards the 
+    /// average or spread of `--repeat`.
+    #[arg(long, value_name = "N", default_value = "0", global = true)]
+    warmup: u32,
+
+    /// Search every position twice, and warn about any positions where the
+    /// node count or best move differs between the searches. With `--repeat`,
+    /// all of the repeated searches are compared instead. Only meaningful 
+    /// when searching to a fixed depth.
+    #[arg(long, global = true)]
+    check_determinism: bool,
+
+    /// The number of engines to run in parallel, each searching its share of
+    /// the positions. Running more engines than there are cores makes them 
+    /// compete for CPU time, so the nps numbers may degrade.
+    #[arg(short, long, default_value = "1", value_parser = clap::value_parser!(u32).range(1..), global = true)]
+    jobs: u32,
 
     /// The file to write the snapshot to
-    #[arg(short, long, default_value = "./bench_snapshot.json")]
+    #[arg(short, long, default_value = DEFAULT_SNAPSHOT, global = true)]
     output: PathBuf,
 
-    /// A suite of fens to use
-    #[arg(short, long)]
+    /// A suite of fens to use. Files with an `.epd` extension are parsed as
+    /// EPD, so they can carry `id` and `bm` operations. Lines in other files
+    /// can be labeled as `label;fen`.
+    #[arg(short, long, conflicts_with = "pgn", global = true)]
     fens: Option<PathBuf>,
 
+    /// A file of games in PGN to take the suite from, instead of a suite of
+    /// fens. Positions are sampled from every game, every `--sample-every` 
+    /// plies.
+    #[arg(long, value_name = "PATH", global = true)]
+    pgn: Option<PathBuf>,
+
+    /// The number of plies between the positions sampled from every game in
+    /// the `--pgn` file
+    #[arg(long, value_name = "N", default_value_t = 10, value_parser = clap::value_parser!(u64).range(1..), requires = "pgn", global = true)]
+    sample_every: u64,
+
+    /// Only run the positions whose label contains this text. Labels are 
+    /// taken from the EPD `id`, or from lines of the form `label;fen`.
+    #[arg(long, value_name = "TEXT", global = true)]
+    filter: Option<String>,
+
+    /// Only run the positions with at least this many pieces on the board,
+    /// kings and pawns included
+    #[arg(long, value_name = "N", global = true)]
+    min_pieces: Option<u32>,
+
+    /// Only run the positions with at most this many pieces on the board,
+    /// kings and pawns included (e.g., 7 for positions in 7-man tablebases)
+    #[arg(long, value_name = "N", global = true)]
+    max_pieces: Option<u32>,
+
+    /// Only run the first `n` positions of the suite, after removing 
+    /// duplicates and applying the filter
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..), global = true)]
+    limit: Option<u64>,
+
+    /// Skip the first `n` positions of the suite, after removing duplicates
+    /// and applying the filter. Combine with `--limit` to run a window of the
+    /// suite.
+    #[arg(long, value_name = "N", default_value_t = 0, global = true)]
+    skip: usize,
+
+    /// Search the positions in a random order, after selecting them with 
+    /// `--skip` and `--limit`
+    #[arg(long, global = true)]
+    shuffle: bool,
+
+    /// The seed to shuffle the positions with, to repeat the order of an 
+    /// earlier run. A random seed is picked (and printed) otherwise.
+    #[arg(long, value_name = "N", requires = "shuffle", global = true)]
+    seed: Option<u64>,
+
     /// An existing snapshot to compare against
-    #[arg(short, long, default_value = "./bench_snapshot.json")]
+    #[arg(short, long, default_value = DEFAULT_SNAPSHOT, global = true)]
     snapshot: PathBuf,
 
+    /// Run the suite without comparing against a snapshot, even if there is 
+    /// one. Use with `--save` to replace an existing snapshot with a new 
+    /// baseline.
+    #[arg(long, visible_alias = "no-compare", global = true)]
+    force_suite: bool,
+
+    /// Leave the positions with an outlying search time out of the summary
+    /// row. The outliers are still printed, but flagged, and are saved to the
+    /// snapshot as usual. The rule is `iqr` (the default) or `z-score`.
+    #[arg(long, value_enum, value_name = "RULE", num_args = 0..=1, require_equals = true, default_missing_value = "iqr", global = true)]
+    drop_outliers: Option<OutlierRule>,
+
+    /// The format to print the results in. CSV output is written to the 
+    /// output file instead of the snapshot when saving. Markdown is handy for
+    /// pasting into a PR.
+    #[arg(long, value_enum, default_value_t = Format::Table, global = true)]
+    format: Format,
+
+    /// How to summarize the metrics of all the positions in the last row
+    #[arg(long, value_enum, default_value_t = Aggregate::Mean, global = true)]
+    aggregate: Aggregate,
+
+    /// Print node counts and times in a human-readable form, e.g., `1.23G` 
+    /// and `1m23s`. CSV and JSON output are left as plain numbers.
+    #[arg(long, global = true)]
+    human: bool,
+
+    /// Only print the summary row, rather than a row for every position. The
+    /// per-position results are still saved and compared.
+    #[arg(short, long, global = true)]
+    quiet: bool,
+
+    /// Sort the rows by the given metric, rather than printing them as they
+    /// come in
+    #[arg(long, value_enum, global = true)]
+    sort_by: Option<SortKey>,
+
+    /// Sort the rows in descending order
+    #[arg(long, requires = "sort_by", global = true)]
+    sort_desc: bool,
+
+    /// When comparing against a snapshot, sort by the size of the relative
+    /// change instead, to surface the biggest changes
+    #[arg(long, requires = "sort_by", global = true)]
+    sort_relative: bool,
+
+    /// Disable colored output. Also disabled when the `NO_COLOR` environment
+    /// variable is set, or when stdout isn't a terminal.
+    #[arg(long, global = true)]
+    no_color: bool,
+
+    /// When to color the output. With `always`, colors are kept even when 
+    /// stdout isn't a terminal, or when writing to a `--report` file.
+    #[arg(long, value_enum, value_name = "WHEN", default_value_t = ColorChoice::Auto, conflicts_with = "no_color", global = true)]
+    color: ColorChoice,
+
+    /// Write the report (the table and the notes around it) to this file 
+    /// instead of stdout. Colors are stripped, unless passing `--color 
+    /// always`. The progress bar and warnings still go to stderr.
+    #[arg(long, value_name = "PATH", global = true)]
+    report: Option<PathBuf>,
+
+    /// A SQLite database to append the results of the run to, along with the
+    /// engine and settings, to track the metrics over many runs with the 
+    /// `trend` subcommand. The tables are created when they don't exist yet.
+    #[cfg(feature = "history")]
+    #[arg(long, value_name = "PATH", global = true)]
+    db: Option<PathBuf>,
+
+    /// The commit the engine was built from, recorded along with the run in
+    /// the `--db` database
+    #[cfg(feature = "history")]
+    #[arg(long, value_name = "REV", requires = "db", global = true)]
+    commit: Option<String>,
+
     /// Write snapshot to output file
-    #[arg(short = 'S', long)]
+    #[arg(short = 'S', long, global = true)]
     save: bool,
 
+    /// Echo the UCI conversation with the engine to stderr. Pass `-vv` to 
+    /// also echo any output that isn't a UCI message.
+    #[arg(short, long, action = clap::ArgAction::Count, global = true)]
+    verbose: u8,
+
+    /// Check that the suite parses, and that every engine starts up and 
+    /// accepts the options, without searching anything
+    #[arg(long, global = true)]
+    dry_run: bool,
+
+    /// Save the results to the output file after every search, and skip the
+    /// positions that are already in it, so an interrupted run can pick up 
+    /// where it left off. Implies `--save`.
+    #[arg(long, global = true)]
+    resume: bool,
+
     /// Output all of the available metrics at once
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     all: bool,
 
     /// Whether or not to include node count in the output
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     nodes: bool,
 
     /// Whether or not to include time in the output
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     time: bool,
 
     /// Whether or not to include time in the output
-    #[arg(short = 'N', long)]
+    #[arg(short = 'N', long, global = true)]
     nps: bool,
 
     /// Whether or not to include the branching factor in the output
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     branching: bool,
 
+    /// Whether or not to include the effective branching factor in the 
+    /// output: the ratio between the node counts of the last two depths the
+    /// engine reported
+    #[arg(long, global = true)]
+    ebf: bool,
+
+    /// Whether or not to include the depth the engine reached in the output
+    #[arg(long, global = true)]
+    reached_depth: bool,
+
+    /// Whether or not to include the selective depth in the output
+    #[arg(long, global = true)]
+    seldepth: bool,
+
+    /// Whether or not to include how full the hash table got in the output
+    #[arg(long, global = true)]
+    hashfull: bool,
+
+    /// Whether or not to include the number of tablebase hits in the output
+    #[arg(long, global = true)]
+    tbhits: bool,
+
     /// Whether or not to include the score in the output
-    #[arg(short = 'E', long)]
+    #[arg(short = 'E', long, global = true)]
     score: bool,
 
     /// Whether or not to include the best move in the output
-    #[arg(short = 'B', long)]
+    #[arg(short = 'B', long, global = true)]
     best_move: bool,
-}
 
-fn main() -> anyhow::Result<()> {
-    Cli::parse().run()
+    /// Whether or not to include the principal variation in the output
+    #[arg(short, long, global = true)]
+    pv: bool,
+
+    /// Sweep a UCI option over a range of values (`NAME=START..END:STEP`), or
+    /// a list of them (`NAME=A,B,C`), running the suite once per value. 
+    /// Reports the score of every position for every value, and ranks the 
+    /// values on the summary of the suite. Repeat the flag to sweep every 
+    /// combination of several options. Ignores any existing snapshot.
+    #[arg(long, visible_alias = "sweep", value_name = "NAME=VALUES", value_parser = OptionSweep::from_str, global = true)]
+    sweep_option: Vec<OptionSweep>,
+
+    /// Search this many principal variations per position, and show each of
+    /// them on a separate line
+    #[arg(long, default_value = "1", value_parser = clap::value_parser!(u32).range(1..), global = true)]
+    multipv: u32,
+
+    /// The size of the engine's transposition table, in MB
+    #[arg(long, value_name = "MB", global = true)]
+    hash: Option<usize>,
+
+    /// Keep the hash table between positions, by only sending `ucinewgame` 
+    /// before the first one, rather than before every position
+    #[arg(long, global = true)]
+    shared_hash: bool,
+
+    /// Show how long the engine took to first reach this depth on every 
+    /// position, going by the depths it reported along the way. Positions
+    /// where it never got there show `—`.
+    #[arg(long, value_name = "D", global = true)]
+    target_depth: Option<usize>,
+
+    /// Store every iteration the engine reports (depth, score, nodes, ...)
+    /// with the results, rather than only the final one, to analyze how the
+    /// search converged. Makes the snapshot a lot bigger.
+    #[arg(long, global = true)]
+    record_progress: bool,
+
+    /// The number of threads the engine should search with
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..), global = true)]
+    threads: Option<u32>,
+
+    /// Load Syzygy tablebases from the given directory (or several, separated
+    /// like the PATH variable), passed to the engine as `SyzygyPath`
+    #[arg(long, value_name = "PATH", global = true)]
+    syzygy: Option<String>,
+
+    /// Evaluate with the given NNUE network, passed to the engine as 
+    /// `EvalFile`. Repeat the flag to benchmark several networks side by
+    /// side, or pass one for every engine to pair them up.
+    #[arg(long, value_name = "PATH", global = true)]
+    eval_file: Vec<String>,
+
+    /// Accept Chess960 positions, and switch the engine to Chess960 with 
+    /// `UCI_Chess960`. Shredder-FEN castling rights (e.g., `HAha`) are 
+    /// rewritten to X-FEN (`KQkq`), and the original field isn't kept.
+    #[arg(long, global = true)]
+    chess960: bool,
+
+    /// Set a UCI option on the engine, given as `name=value`, or just `name`
+    /// for button options. Can be passed multiple times.
+    #[arg(long = "option", visible_alias = "setoption", value_name = "NAME=VALUE", value_parser = EngineOption::from_str, global = true)]
+    options: Vec<EngineOption>,
+
+    /// Skip positions in the suite that fail to parse, rather than refusing to
+    /// run
+    #[arg(long, global = true)]
+    skip_invalid: bool,
+
+    /// Remove duplicate positions from the suite, keeping the first occurrence
+    #[arg(long, global = true)]
+    dedupe: bool,
+
+    /// Ignore the half-move and full-move counters when looking for duplicate
+    /// positions
+    #[arg(long, requires = "dedupe", global = true)]
+    ignore_counters: bool,
+
+    /// Exit with an error when the averaged gate metric regressed by more than
+    /// this percentage compared to the snapshot
+    #[arg(long, value_name = "PCT", global = true)]
+    fail_on_regression: Option<f32>,
+
+    /// With `--format github`, annotate the positions whose gate metric
+    /// regressed by more than this percentage compared to the snapshot
+    #[arg(long, value_name = "PCT", default_value_t = 5.0, global = true)]
+    warn_on_regression: f32,
+
+    /// The metric that decides whether the run counts as a regression
+    #[arg(long, value_enum, default_value_t = GateMetric::Nps, global = true)]
+    gate_metric: GateMetric,
+
+    /// When comparing against a snapshot, run a paired t-test on the nps of
+    /// every position, to tell whether the change in nps stands out from the
+    /// noise
+    #[arg(long, global = true)]
+    significance: bool,
+
+    /// The significance level for `--significance`
+    #[arg(long, value_name = "ALPHA", default_value_t = 0.05, requires = "significance", global = true)]
+    alpha: f64,
+
+    /// Flip the direction in which a metric counts as worse, for coloring
+    /// diffs and gating on regressions, e.g., `nodes=lower` when more nodes
+    /// are expected. The metric is one of `nodes`, `time`, `nps`, `branching`
+    /// or `score`. Repeat the flag to override several metrics.
+    #[arg(long, value_name = "METRIC=lower|higher", global = true)]
+    worse_is: Vec<WorseIs>,
+
+    /// Exit with an error if the engine misses the `bm` of any position in an
+    /// EPD suite
+    #[arg(long, global = true)]
+    require_all_solved: bool,
+
+    /// Give up on a position if the engine hasn't returned a best move after
+    /// this many milliseconds. Disabled by default.
+    #[arg(long, value_name = "MS", global = true)]
+    timeout: Option<u64>,
+
+    /// Write a JUnit XML report to this file, with a test case per position
+    /// that fails when the engine missed the best move, or when the gate 
+    /// metric regressed by more than `--fail-on-regression`
+    #[arg(long, value_name = "PATH", global = true)]
+    junit: Option<PathBuf>,
+
+    /// Append the engine's stderr output to this file, tagged with the
+    /// position being searched. Discarded by default.
+    #[arg(long, value_name = "PATH", global = true)]
+    engine_log: Option<PathBuf>,
+
+    /// An argument to pass to the engine binary when starting it (e.g., 
+    /// `--engine-arg --no-banner`). Repeat the flag to pass several arguments,
+    /// in order.
+    #[arg(long, value_name = "ARG", allow_hyphen_values = true, global = true)]
+    engine_arg: Vec<String>,
+
+    /// The directory to start the engine in, e.g., so it can find files it
+    /// loads from a relative path. Defaults to the current directory.
+    #[arg(long, value_name = "DIR", global = true)]
+    engine_cwd: Option<PathBuf>,
+
+    /// The number of times to restart the engine when it crashes, before 
+    /// giving up on the run
+    #[arg(long, default_value = "3", global = true)]
+    max_restarts: usize,
+
+    /// Overwrite the snapshot being compared against without asking for
+    /// confirmation
+    #[arg(short, long, global = true)]
+    yes: bool,
 }
 
-impl Cli {
-    /// Run the program either in Snapshot mode or Suite mode, depending on the
-    /// CLI arguments
-    pub fn run(&self) -> anyhow::Result<()> {
-        let results = if let Ok(file) = File::open(self.snapshot.as_path()) {
-            let file = BufReader::new(file);
-            let snapshot: Vec<SearchResult> = serde_json::from_reader(file)?;
+/// Benchmarks that don't follow the regular suite or snapshot flow. All of 
+/// the regular flags apply to these as well.
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Compare two engines over the same suite, without a snapshot
+    ///
+    /// Runs the first engine over the suite, and then compares the second 
+    /// engine against its results, as if they had been saved to a snapshot.
+    /// Pass `--save` to write the results of the second engine to the output
+    /// file.
+    Diff {
+        /// The engine to compare against
+        first: PathBuf,
 
-            self.run_snapshot(&snapshot)
-        } else {
-            let suite: Vec<String> = if let Some(file) = &self.fens {
-                std::fs::read_to_string(file)
-                    .unwrap()
-                    .lines()
-                    .map(|st| st.to_owned())
-                    .collect()
-            } else {
-                POSITIONS.into_iter().map(|st| st.to_owned()).collect()
-            };
+        /// The engine to compare to the first one
+        second: PathBuf,
 
-            self.run_suite(&suite)
-        }?;
+        /// Also write the results of the first engine to a snapshot
+        #[arg(long, value_name = "PATH")]
+        save_first: Option<PathBuf>,
+    },
 
-        // Save the results to the requested output file
-        if self.save {
-            write(self.output.as_path(), serde_json::to_string(&results)?)?;
-        }
+    /// Count the leaf nodes of every position to a fixed depth (perft)
+    ///
+    /// Perft isn't part of UCI, so the engine needs to support it through a 
+    /// custom command (e.g., `go perft <depth>`), and end its output with a 
+    /// `Nodes searched: <n>` line.
+    Perft {
+        /// The engine to run perft with
+        engine: PathBuf,
 
-        Ok(())
-    }
+        /// The depth to count the nodes to
+        #[arg(short, long)]
+        depth: usize,
 
-    /// Run the engine against a snapshot of SearchResults and return the
-    /// Vec of new SearchResults. 
+        /// The command that starts a perft. `{depth}` is replaced by the 
+        /// requested depth.
+        #[arg(long, default_value = "go perft {depth}")]
+        command: String,
+    },
+
+    /// Print the results stored in a snapshot, without running the engine
     ///
-    /// Also responsible for reporting/printing the results as they come in.
-    fn run_snapshot(&self, snapshot: &[SearchResult]) -> anyhow::Result<Vec<SearchResult>> {
-        let mut results = Vec::new();
-        let mut diffs = Vec::new();
-        let mut engine = Engine::new(&self.engine)?;
+    /// Reads the snapshot passed with `--snapshot`, and prints it in the 
+    /// requested format, with the requested fields.
+    Show,
 
-        let fields = Fields::from(self);
+    /// Print how the metrics changed over the runs recorded in a database
+    ///
+    /// Reads the database passed with `--db`, and prints the average nodes,
+    /// time and nps of every recorded run, oldest first, along with the 
+    /// change in nps since the run before.
+    #[cfg(feature = "history")]
+    Trend {
+        /// Only count the results for this position, given as its FEN or its
+        /// label
+        #[arg(long, value_name = "FEN")]
+        position: Option<String>,
+    },
+}
 
-        let mut table = Tabulator::new();
+fn main() -> anyhow::Result<()> {
+    let mut cli = config::parse_args()?;
+    diff::set_worse_is(cli.worse_is.clone());
 
-        table.add_col("FEN", 72);
+    // Settle on a seed up front, so every list of positions gets shuffled the
+    // same way
+    if cli.shuffle {
+        let seed = *cli.seed.get_or_insert_with(rand::random);
+        eprintln!("Shuffling the positions with seed {seed} (pass --seed {seed} to repeat this order)");
+    }
+    engine::set_verbosity(cli.verbose);
 
-        if fields.nodes {
-            table.add_col("Nodes", 45);
-        }
+    // Escape codes are just noise when the output is piped to a file or CI log
+    let no_color = cli.no_color 
+        || report::no_color_requested(std::env::var_os("NO_COLOR").as_deref())
+        || !std::io::stdout().is_terminal();
 
-        if fields.time {
-            table.add_col("Time", 30);
-        }
+    if let Some(colorize) = cli.color.colorize(no_color) {
+        colored::control::set_override(colorize);
+    }
 
-        if fields.nps {
-            table.add_col("Nps", 30);
-        }
+    interrupt::install()?;
+    let result = cli.run();
 
-        if fields.branching {
-            table.add_col("Branching Factor", 25);
+    // Whatever was searched before the interrupt has been reported (and 
+    // saved) by now
+    if interrupt::interrupted() {
+        if let Err(err) = &result {
+            eprintln!("Error: {err:?}");
         }
 
-        if fields.score {
-            table.add_col("Score", 15);
+        eprintln!("{} the run was interrupted, the results are incomplete", "Warning:".yellow());
+        std::process::exit(interrupt::EXIT_CODE);
+    }
+
+    result
+}
+
+/// Set an option on the engine, warning when the engine doesn't advertise it.
+/// The option is sent regardless, in case the engine just forgot to list it.
+fn set_engine_option(engine: &mut Engine, name: &str, value: &str) -> anyhow::Result<()> {
+    if !engine.has_option(name) {
+        eprintln!("{} the engine does not advertise the '{name}' option", "Warning:".yellow());
+    }
+
+    engine.set_option(name, value)
+}
+
+/// Check that every directory in a Syzygy path exists, so a typo doesn't 
+/// silently run the suite without tablebases
+fn check_syzygy_path(syzygy: &str) -> anyhow::Result<()> {
+    for dir in std::env::split_paths(syzygy) {
+        if !dir.is_dir() {
+            return Err(anyhow!("Syzygy directory {} does not exist", dir.display()));
         }
+    }
 
-        println!("{}", table.header());
+    Ok(())
+}
 
-        for snapshot_result in snapshot {
-            let board = snapshot_result.position.parse()?;
-            let result = engine.search(board, snapshot_result.depth)?;
-            let diff = Diff::new(snapshot_result, &result);
+/// Pick the setting passed on the command line if there is one, falling back
+/// to the one stored in a snapshot. Warns when the two differ.
+fn inherit_setting<T: PartialEq + Display>(
+    name: &str, 
+    cli: Option<T>, 
+    snapshot: Option<T>
+) -> Option<T> {
+    match (cli, snapshot) {
+        (Some(cli), Some(snapshot)) if cli != snapshot => {
+            eprintln!(
+                "{} using {name} = {cli}, but the snapshot was taken with {name} = {snapshot}",
+                "Warning:".yellow()
+            );
 
-            // Print the diff in a table
-            let row = diff.extract(&fields);
-            println!("{}", table.row(&row));
+            Some(cli)
+        },
 
-            // Store the result
-            results.push(result);
-            diffs.push(diff);
+        (cli, snapshot) => cli.or(snapshot),
+    }
+}
+
+/// Use the list of settings (e.g., UCI options) stored in the snapshot, 
+/// unless any were passed explicitly. Warns when the two differ.
+fn inherit_list<T: PartialEq + Display + Clone>(name: &str, cli: &[T], snapshot: &[T]) -> Vec<T> {
+    if cli.is_empty() {
+        return snapshot.to_vec();
+    }
+
+    if cli != snapshot {
+        let list = |values: &[T]| values
+            .iter()
+            .map(|value| value.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        eprintln!(
+            "{} using {name} [{}], but the snapshot was taken with [{}]",
+            "Warning:".yellow(),
+            list(cli),
+            list(snapshot),
+        );
+    }
+
+    cli.to_vec()
+}
+
+/// Check whether a line in a suite file should be skipped: blank lines, and
+/// comments starting with `#` or `//`
+fn is_comment(line: &str) -> bool {
+    line.is_empty() || line.starts_with('#') || line.starts_with("//")
+}
+
+/// Normalize a FEN for comparison, optionally dropping the move counters
+fn normalize_fen(fen: &str, ignore_counters: bool) -> String {
+    let fields = if ignore_counters { 4 } else { 6 };
+
+    fen.split_whitespace()
+        .take(fields)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The search limit to use, given the settings for the run. Searches to the 
+/// requested depth, unless a movetime or node limit was configured.
+fn time_control(meta: &Metadata, depth: usize) -> TimeControl {
+    if let Some(movetime) = meta.movetime {
+        TimeControl::FixedTime(Duration::from_millis(movetime))
+    } else if let Some(nodes) = meta.max_nodes {
+        TimeControl::Nodes(nodes)
+    } else {
+        TimeControl::Depth(depth)
+    }
+}
+
+/// Group the results by position. A position is in a snapshot once for every
+/// depth it was searched at, right after each other.
+fn by_position(results: Vec<SearchResult>) -> Vec<Vec<SearchResult>> {
+    results.chunk_by(|first, second| first.position == second.position)
+        .map(<[SearchResult]>::to_vec)
+        .collect()
+}
+
+/// The searches to run for a suite: every position at each of the depths,
+/// along with the root moves to restrict the search to, if any
+fn suite_searches<'a>(
+    suite: &'a [Epd], 
+    searchmoves: &'a [Vec<String>], 
+    depths: &[usize]
+) -> Vec<(&'a str, usize, &'a [String])> {
+    suite.iter()
+        .zip(searchmoves)
+        .flat_map(|(epd, moves)| depths.iter()
+            .map(|&depth| (epd.fen.as_str(), depth, moves.as_slice()))
+        )
+        .collect()
+}
+
+/// A table row marking a position the engine crashed or timed out on
+fn failed_row(report: &Report, fen: &str) -> Vec<String> {
+    let mut row = vec![format!("{}", fen.red())];
+    row.resize(report.cols(), format!("{}", "failed".red()));
+
+    row
+}
+
+/// Print the row for a single result, along with any additional MultiPV lines
+fn print_result(report: &Report, result: &SearchResult, fields: &Fields, outlier: bool) {
+    report.print_row(&flag_outlier(result.extract(fields), outlier));
+
+    for line in result.extract_lines(fields) {
+        report.print_row(&line);
+    }
+}
+
+/// Print the row for a single diff, along with any additional MultiPV lines 
+/// from the new result
+fn print_diff(report: &Report, diff: &Diff, result: &SearchResult, fields: &Fields, outlier: bool) {
+    report.print_row(&flag_outlier(diff.extract(fields), outlier));
+
+    for line in result.extract_lines(fields) {
+        report.print_row(&line);
+    }
+}
+
+/// Put a marker in front of the FEN of a row when the result is left out of
+/// the summary as an outlier, see `--drop-outliers`
+fn flag_outlier(mut row: Vec<String>, outlier: bool) -> Vec<String> {
+    if outlier {
+        row[0] = format!("{} {}", "OUTLIER".yellow(), row[0]);
+    }
+
+    row
+}
+
+/// The items that aren't flagged as outliers
+fn without_outliers<T: Clone>(items: &[T], outliers: &[bool]) -> Vec<T> {
+    items.iter()
+        .zip(outliers)
+        .filter(|(_, &outlier)| !outlier)
+        .map(|(item, _)| item.clone())
+        .collect()
+}
+
+/// Print how many positions were left out of the summary as outliers
+fn print_outliers(report: &Report, outliers: &[bool], rule: OutlierRule) {
+    let count = outliers.iter().filter(|&&outlier| outlier).count();
+
+    report.print_note(&format!(
+        "Left {count}/{} position(s) out of the summary, with a search time {rule}",
+        outliers.len(),
+    ));
+}
+
+/// Print a summary of the positions the engine crashed or timed out on, if any
+fn print_failed(report: &Report, failed: &[String]) {
+    if failed.is_empty() {
+        return;
+    }
+
+    report.print_note(&format!(
+        "{}", 
+        format!("The engine crashed or timed out on {} position(s):", failed.len()).red()
+    ));
+
+    for fen in failed {
+        report.print_note(&format!("  {fen}"));
+    }
+}
+
+/// The row for a result that can't be compared, because its position is only
+/// in the suite (`NEW`) or only in the snapshot (`GONE`). Shows the values of
+/// the result as is, with the marker in front of the FEN.
+fn unmatched_row(result: &SearchResult, fields: &Fields, marker: &str) -> Vec<String> {
+    let mut row = result.extract(fields);
+    row[0] = format!("{} {}", marker.yellow(), result.position.blue());
+
+    row
+}
+
+/// Print a one-line summary of the comparison, headlined by the gate metric,
+/// e.g., `Verdict: 3.2% faster (nps), node count -1.1%`. Prints nothing when
+/// there's no relative change to report.
+fn print_verdict(report: &Report, averages: &Diff, metric: GateMetric, redepth: Option<&Redepth>) {
+    if let Some(verdict) = verdict(averages, metric, redepth) {
+        report.print_note(&verdict);
+    }
+}
+
+/// Print the distribution of the per-position changes in nps and nodes, to
+/// tell a change that helps across the board from one that helps a few 
+/// positions a lot. The mean is in the summary row already.
+fn print_percentiles(report: &Report, diffs: &[Diff]) {
+    let metrics = [
+        ("nps", diffs.iter().filter_map(|diff| diff.nps.relative).collect::<Vec<_>>()),
+        ("nodes", diffs.iter().filter_map(|diff| diff.nodes.relative).collect::<Vec<_>>()),
+    ];
+
+    for (name, changes) in metrics {
+        let changes = changes.into_iter().map(f64::from).collect::<Vec<_>>();
+
+        let percentiles = [50.0, 90.0, 99.0].iter()
+            .filter_map(|&p| stats::percentile(&changes, p)
+                .map(|change| format!("p{p} {:+.2}%", 100.0 * change))
+            )
+            .collect::<Vec<_>>();
+
+        if !percentiles.is_empty() {
+            report.print_note(&format!("Change in {name} per position: {}", percentiles.join(", ")));
         }
+    }
+}
 
-        // Print averages, potentially behind a flag
-        println!("{}", table.row_separator());
-        let averages = diffs.into_iter().sum::<Diff>() / results.len();
-        let averages = averages.extract(&fields);
+/// Print whether the change in nps between the snapshot and the current run
+/// is statistically significant, according to a paired t-test over the 
+/// positions
+fn print_significance(report: &Report, diffs: &[Diff], alpha: f64) {
+    let pairs = diffs.iter()
+        .filter_map(|diff| Some((diff.nps.first.0? as f64, diff.nps.second.0? as f64)))
+        .collect::<Vec<_>>();
 
-        println!("{}", table.row(&averages));
+    let Some(test) = stats::paired_t_test(&pairs) else {
+        report.print_note(&format!(
+            "Not enough positions with an nps in both runs for a significance test ({} found, at least 2 needed)",
+            pairs.len()
+        ));
+        return;
+    };
 
-        // Print footer line
-        println!("{}", table.footer());
+    let verdict = if test.p < alpha { "significant" } else { "not significant" };
 
-        Ok(results)
+    report.print_note(&format!(
+        "Paired t-test on nps over {} positions: t = {:.3}, p = {:.4} ({verdict} at alpha = {alpha})",
+        test.n,
+        test.t,
+        test.p,
+    ));
+}
+
+/// The verdict line for the comparison, see `print_verdict`. When the 
+/// positions were searched at other depths than the snapshot (with 
+/// `--re-depth`), the verdict says so.
+fn verdict(averages: &Diff, metric: GateMetric, redepth: Option<&Redepth>) -> Option<String> {
+    let regression = averages.regression(metric)?;
+    let relative = averages.relative(metric).unwrap_or_default();
+    let change = 100.0 * relative.abs();
+
+    // A higher nps or a lower time means the engine got faster
+    let headline = match metric {
+        _ if relative == 0.0 => format!("no change ({metric})"),
+        GateMetric::Nps if relative > 0.0 => format!("{change:.1}% faster (nps)"),
+        GateMetric::Nps => format!("{change:.1}% slower (nps)"),
+        GateMetric::Time if relative < 0.0 => format!("{change:.1}% faster (time)"),
+        GateMetric::Time => format!("{change:.1}% slower (time)"),
+        GateMetric::Nodes if relative < 0.0 => format!("{change:.1}% fewer nodes"),
+        GateMetric::Nodes => format!("{change:.1}% more nodes"),
+    };
+
+    let color = if regression > 0.0 {
+        Color::Red
+    } else if regression < 0.0 {
+        Color::Green
+    } else {
+        Color::Black
+    };
+
+    // Add the node count when it isn't the headline, since a speedup that 
+    // comes with a different node count is a functional change
+    let detail = match (metric, averages.nodes.relative, averages.nps.relative) {
+        (GateMetric::Nodes, _, Some(nps)) => format!(", nps {:+.1}%", 100.0 * nps),
+        (GateMetric::Nodes, _, None) => String::new(),
+        (_, Some(nodes), _) => format!(", node count {:+.1}%", 100.0 * nodes),
+        (_, None, _) => String::new(),
+    };
+
+    let mismatch = match redepth {
+        Some((from, to)) => {
+            let mismatch = format!(
+                "(depth mismatch: searched at depth {}, snapshot at depth {})",
+                depth_list(to),
+                depth_list(from)
+            );
+
+            format!(" {}", mismatch.yellow())
+        },
+        None => String::new(),
+    };
+
+    Some(format!("Verdict: {}{detail}{mismatch}", headline.color(color)))
+}
+
+/// The depths the snapshot was taken at, and the depths they're searched at
+/// instead with `--re-depth`, in the same order
+type Redepth = (Vec<usize>, Vec<usize>);
+
+/// A list of depths, as passed to `--depth`
+fn depth_list(depths: &[usize]) -> String {
+    depths.iter()
+        .map(usize::to_string)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// The GitHub Actions annotation for a position whose gate metric regressed,
+/// as an error when it regressed by more than the `fail` percentage, or as a 
+/// warning when it regressed by more than the `warn` percentage.
+fn annotation(diff: &Diff, metric: GateMetric, warn: f32, fail: Option<f32>) -> Option<String> {
+    let regression = 100.0 * diff.regression(metric)?;
+    let relative = 100.0 * diff.relative(metric)?;
+
+    let command = match fail {
+        Some(fail) if regression > fail => "error",
+        _ if regression > warn => "warning",
+        _ => return None,
+    };
+
+    let (first, second) = match metric {
+        GateMetric::Nps => (diff.nps.first.to_string(), diff.nps.second.to_string()),
+        GateMetric::Nodes => (diff.nodes.first.to_string(), diff.nodes.second.to_string()),
+        GateMetric::Time => (diff.time.first.to_string(), diff.time.second.to_string()),
+    };
+
+    Some(report::workflow_command(
+        command,
+        "Regression",
+        &format!(
+            "{} (depth {}): {metric} {relative:+.2}% ({first} -> {second})", 
+            diff.position, 
+            diff.depth
+        ),
+    ))
+}
+
+/// Print how many positions couldn't be compared, because they're only in the
+/// suite, or only in the snapshot. Prints nothing when all of them matched.
+fn print_unmatched(report: &Report, new: usize, gone: usize) {
+    if new > 0 || gone > 0 {
+        report.print_note(&format!(
+            "{new} new position(s) not in the snapshot, {gone} position(s) gone from the suite"
+        ));
     }
+}
 
-    /// Run a suite of board positions through the engine, and return a Vec
-    /// of SearchResult.
-    ///
-    /// Also responsible for reporting/printing the results as they come in.
-    fn run_suite(&self, suite: &[String]) -> anyhow::Result<Vec<SearchResult>> {
-        let mut results = Vec::new();
-        let mut engine = Engine::new(&self.engine)?;
+/// Print how many of the positions with a `bm` operation the engine solved, 
+/// and how long it spent on them in total. Prints nothing when there aren't any.
+fn print_solved(report: &Report, results: &[SearchResult]) {
+    let annotated = results.iter()
+        .filter(|result| result.solved().is_some())
+        .collect::<Vec<_>>();
 
-        let fields = Fields::from(self);
+    if annotated.is_empty() {
+        return;
+    }
 
-        let mut table = Tabulator::new();
+    let solved = annotated.iter()
+        .filter(|result| result.solved() == Some(true))
+        .collect::<Vec<_>>();
 
-        table.add_col("FEN", 72);
+    let time = solved.iter()
+        .map(|result| result.time)
+        .fold(Time::default(), |acc, time| acc + time);
 
-        if fields.nodes {
-            table.add_col("Nodes", 20);
+    report.print_note(&format!(
+        "Solved {}/{} positions, total time to solution: {time}", 
+        solved.len(), 
+        annotated.len()
+    ));
+}
+
+/// Print how long the whole run took, next to the time the engines spent
+/// searching, so the overhead (engine startup, the UCI back-and-forth, 
+/// warmup searches) stands out
+///
+/// Every result is assumed to have been searched `runs` times, reporting
+/// the average time.
+fn print_total(report: &Report, wall: Duration, results: &[SearchResult], runs: u32) {
+    let searching = results.iter()
+        .map(|result| result.time.0 * runs as u64)
+        .sum::<u64>();
+
+    report.print_note(&format!(
+        "Total: {} (engine time: {})",
+        format_duration(wall),
+        format_duration(Duration::from_millis(searching)),
+    ));
+}
+
+/// Format a duration the way a person would read it off a clock, e.g.,
+/// `1h 2m 3s`, `1m 23s`, or `4.2s`
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+
+    match secs {
+        0..=59 => format!("{:.1}s", duration.as_secs_f64()),
+        60..=3599 => format!("{}m {}s", secs / 60, secs % 60),
+        _ => format!("{}h {}m {}s", secs / 3600, secs / 60 % 60, secs % 60),
+    }
+}
+
+/// Print which engine is being benchmarked, as it identified itself, and how
+/// long it took to start up, to catch slow initialization (e.g., loading a 
+/// network)
+fn print_engine(report: &Report, engine: &Engine) {
+    let startup = engine.startup();
+
+    report.print_note(&format!("Engine: {}", engine.id()));
+    report.print_note(&format!(
+        "Engine startup: uciok after {}ms, readyok after {}ms",
+        startup.uciok.as_millis(),
+        startup.readyok.as_millis(),
+    ));
+}
+
+/// Print how many positions gave different results when searched repeatedly,
+/// if any
+fn print_nondeterministic(report: &Report, results: &[SearchResult]) {
+    let count = results.iter()
+        .filter(|result| result.nondeterministic)
+        .count();
+
+    if count > 0 {
+        report.print_note(&format!(
+            "{} {count}/{} positions searched non-deterministically",
+            "Warning:".yellow(),
+            results.len(),
+        ));
+    }
+}
+
+/// Check whether two paths point to the same file on disk
+fn same_file(first: &Path, second: &Path) -> bool {
+    match (first.canonicalize(), second.canonicalize()) {
+        (Ok(first), Ok(second)) => first == second,
+        _ => first == second,
+    }
+}
+
+/// Ask the user to confirm overwriting a file. Errors out when the user 
+/// declines, or when there's no terminal to ask on.
+fn confirm_overwrite(path: &Path) -> anyhow::Result<()> {
+    if !std::io::stdin().is_terminal() {
+        return Err(anyhow!(
+            "Refusing to overwrite snapshot {} that is being compared against. Pass --yes to overwrite anyway.",
+            path.display()
+        ));
+    }
+
+    eprint!("This will overwrite the snapshot {} being compared against. Continue? [y/N] ", path.display());
+    std::io::stderr().flush()?;
+
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+
+    match answer.trim().to_lowercase().as_str() {
+        "y" | "yes" => Ok(()),
+        _ => Err(anyhow!("Aborted, snapshot was not overwritten")),
+    }
+}
+
+impl Cli {
+    /// Run the program either in Snapshot mode or Suite mode, depending on the
+    /// CLI arguments
+    pub fn run(&self) -> anyhow::Result<()> {
+        if self.dry_run {
+            return self.run_dry();
         }
 
-        if fields.time {
-            table.add_col("Time", 10);
+        // Writing CSV over a snapshot would leave it unreadable for the next
+        // run, so the CSV needs a file of its own
+        let overwrites_snapshot = same_file(&self.output, &self.snapshot)
+            || same_file(&self.output, Path::new(DEFAULT_SNAPSHOT));
+
+        if self.save && self.format == Format::Csv && overwrites_snapshot {
+            return Err(anyhow!(
+                "--format csv --save would write CSV to {}, where snapshots are loaded from. Pass --output to give the CSV a file of its own",
+                self.output.display()
+            ));
         }
 
-        if fields.nps {
-            table.add_col("Nps", 10);
+        if let Some(Command::Diff { first, second, save_first }) = &self.command {
+            return self.run_diff(first, second, save_first.as_deref());
         }
 
-        if fields.branching {
-            table.add_col("Branching", 10);
+        if let Some(Command::Perft { engine, depth, command }) = &self.command {
+            return self.run_perft(engine, *depth, command);
         }
 
-        if fields.score {
-            table.add_col("Score", 10);
+        if let Some(Command::Show) = &self.command {
+            return self.run_show();
         }
 
-        println!("{}", table.header());
+        #[cfg(feature = "history")]
+        if let Some(Command::Trend { position }) = &self.command {
+            return self.run_trend(position.as_deref());
+        }
 
-        for fen in suite {
-            let board = fen.parse()?;
-            let result = engine.search(board, self.depth)?;
+        let engines = self.engine.iter()
+            .chain(&self.engines)
+            .map(PathBuf::as_path)
+            .collect::<Vec<_>>();
 
-            let row = result.extract(&fields);
-            println!("{}", table.row(&row));
+        let runs = self.runs(&engines)?;
 
-            results.push(result);
+        if runs.len() > 1 {
+            return self.run_matrix(&runs, &self.load_suite()?);
         }
 
-        // Print averages, potentially behind a flag
-        println!("{}", table.row_separator());
-        let averages = results.clone().into_iter().sum::<SearchResult>() / results.len();
-        let averages = averages.extract(&fields);
+        // Clap makes sure there's an engine when there's no subcommand
+        let engine = engines.first()
+            .ok_or_else(|| anyhow!("No engine was given"))?;
+
+        if !self.sweep_option.is_empty() {
+            return self.run_sweep(engine, &self.load_suite()?, &self.sweep_option);
+        }
 
-        println!("{}", table.row(&averages));
+        let mut meta = self.metadata();
 
-        // Print footer line
-        println!("{}", table.footer());
+        let mut averages = None;
 
-        Ok(results)
+        if self.report.is_some() && self.format.is_machine_readable() {
+            return Err(anyhow!("--report writes out the table, so it can't be combined with --format csv or json"));
+        }
+
+        if self.resume && self.format == Format::Csv {
+            return Err(anyhow!("--resume saves a snapshot, so it can't be combined with --format csv"));
+        }
+
+        let results = if !self.force_suite && !self.resume && self.snapshot.exists() {
+            if self.save && !self.yes && same_file(&self.output, &self.snapshot) {
+                confirm_overwrite(&self.output)?;
+            }
+
+            let snapshot = Snapshot::load(&self.snapshot)?;
+            meta = self.inherit_metadata(&snapshot.meta);
+
+            let baseline = snapshot.results.into_iter()
+                .filter(|result| self.matches_filter(result.id.as_deref()))
+                .collect::<Vec<_>>();
+
+            if baseline.is_empty() && self.filter.is_some() {
+                return Err(anyhow!("No positions in the snapshot match the filter"));
+            }
+
+            // The suite reports how many positions were dropped
+            let baseline = self.filter_pieces(by_position(baseline), |results| &results[0].position)
+                .concat();
+
+            if baseline.is_empty() {
+                return Err(anyhow!("No positions in the snapshot have the requested number of pieces"));
+            }
+
+            // When a suite (or a PGN) is passed explicitly, it decides which positions
+            // get compared, in which order
+            let (baseline, new, gone) = match (&self.fens, &self.pgn) {
+                (None, None) => self.complete_snapshot(self.window_results(baseline))?,
+                _ => self.match_window(baseline)?,
+            };
+
+            if baseline.is_empty() {
+                return Err(anyhow!("None of the positions in the suite are in the snapshot"));
+            }
+
+            let (results, diff) = self.run_snapshot(engine, &baseline, &new, &gone, &mut meta)?;
+            averages = Some(diff);
+            results
+        } else if self.resume && self.output.exists() {
+            let snapshot = Snapshot::load(&self.output)?;
+            meta = self.inherit_metadata(&snapshot.meta);
+
+            let mut suite = self.load_suite()?;
+            let done = self.skip_done(&mut suite, snapshot.results, &meta)?;
+
+            eprintln!(
+                "Resuming from {}: {} search(es) done, {} position(s) left",
+                self.output.display(),
+                done.len(),
+                suite.len()
+            );
+
+            self.run_suite(engine, &suite, done, &mut meta)?
+        } else {
+            self.run_suite(engine, &self.load_suite()?, Vec::new(), &mut meta)?
+        };
+
+        self.finish_run(meta, results, averages)
+    }
+
+    /// Check that everything is in place for a run, without searching: the 
+    /// suite (and the snapshot, if there is one) can be read, and every 
+    /// engine starts, completes the UCI handshake, and accepts the options.
+    fn run_dry(&self) -> anyhow::Result<()> {
+        let engines = match &self.command {
+            Some(Command::Diff { first, second, .. }) => vec![first.as_path(), second.as_path()],
+            Some(Command::Perft { engine, .. }) => vec![engine.as_path()],
+            Some(Command::Show) => Vec::new(),
+
+            #[cfg(feature = "history")]
+            Some(Command::Trend { .. }) => Vec::new(),
+            None => self.engine.iter().chain(&self.engines).map(PathBuf::as_path).collect(),
+        };
+
+        let suite = self.load_suite()?;
+        println!("Parsed {} position(s)", suite.len());
+
+        let mut meta = self.metadata();
+
+        if !self.force_suite && !self.resume && self.snapshot.exists() {
+            let snapshot = Snapshot::load(&self.snapshot)?;
+            meta = self.inherit_metadata(&snapshot.meta);
+
+            println!(
+                "Loaded {} result(s) from {}", 
+                snapshot.results.len(), 
+                self.snapshot.display()
+            );
+        }
+
+        let mut broken = 0;
+
+        for (engine, eval_file) in self.runs(&engines)? {
+            let mut meta = meta.clone();
+
+            if eval_file.is_some() {
+                meta.eval_file = eval_file;
+            }
+
+            match self.start_engine(engine, &meta) {
+                Ok(started) => println!("Started {} ({})", engine.display(), started.id()),
+
+                Err(err) => {
+                    broken += 1;
+                    eprintln!("{} {}: {err}", "Error:".red(), engine.display());
+                },
+            }
+        }
+
+        if broken > 0 {
+            return Err(anyhow!("{broken} engine(s) failed to start"));
+        }
+
+        println!("Everything is in place, nothing was searched");
+
+        Ok(())
+    }
+
+    /// Run both engines over the suite, and compare the results of the second
+    /// engine against those of the first, as if the first had been saved to a
+    /// snapshot.
+    fn run_diff(&self, first: &Path, second: &Path, save_first: Option<&Path>) -> anyhow::Result<()> {
+        let suite = self.load_suite()?;
+        let mut meta = self.metadata();
+
+        // Either both engines use the same network, or each gets its own
+        let runs = self.runs(&[first, second])?;
+        meta.eval_file = runs[0].1.clone();
+
+        let baseline = self.search_suite(first, &suite, &mut meta)?;
+
+        if let Some(path) = save_first {
+            let snapshot = Snapshot::new(self.stamp(meta.clone(), &baseline), baseline.clone());
+            write(path, serde_json::to_string(&snapshot)?)?;
+        }
+
+        meta.eval_file = runs[1].1.clone();
+        let (results, diff) = self.run_snapshot(second, &baseline, &[], &[], &mut meta)?;
+
+        self.finish_run(meta, results, Some(diff))
+    }
+
+    /// Write out the results if requested, and check them against any of the
+    /// requested gates
+    fn finish_run(
+        &self, 
+        meta: Metadata, 
+        results: Vec<SearchResult>, 
+        averages: Option<Diff>
+    ) -> anyhow::Result<()> {
+        let missed = results.iter()
+            .filter(|result| result.solved() == Some(false))
+            .count();
+
+        if self.format == Format::Csv {
+            let mut fields = Fields::from(self);
+            fields.label = results.iter().any(|result| result.id.is_some());
+            fields.solved = results.iter().any(|result| result.solved().is_some());
+
+            let csv = report::to_csv(&results, &fields);
+
+            if self.save {
+                write(self.output.as_path(), csv)?;
+            } else {
+                print!("{csv}");
+            }
+        } else if self.save || self.resume {
+            // Save the results to the requested output file
+            let snapshot = Snapshot::new(self.stamp(meta.clone(), &results), results.clone());
+            write(self.output.as_path(), serde_json::to_string(&snapshot)?)?;
+        }
+
+        #[cfg(feature = "history")]
+        if let Some(path) = &self.db {
+            let history = History::open(path)?;
+            let run = history.record(&self.stamp(meta, &results), self.commit.as_deref(), &results)?;
+            eprintln!("Recorded the results as run {run} in {}", path.display());
+        }
+
+        if self.require_all_solved && missed > 0 {
+            return Err(anyhow!("The engine missed the best move in {missed} position(s)"));
+        }
+
+        let regression = averages.and_then(|diff| diff.regression(self.gate_metric));
+
+        if let (Some(threshold), Some(regression)) = (self.fail_on_regression, regression) {
+            if 100.0 * regression > threshold {
+                return Err(anyhow!(
+                    "The average {} regressed by {:.2}%, more than the allowed {threshold}%",
+                    self.gate_metric,
+                    100.0 * regression,
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Complete the metadata of a finished run before saving it: when it ran,
+    /// and which depths it searched, when searching to a fixed depth
+    fn stamp(&self, mut meta: Metadata, results: &[SearchResult]) -> Metadata {
+        meta.timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .ok()
+            .map(|elapsed| elapsed.as_secs());
+
+        if meta.movetime.is_none() && meta.max_nodes.is_none() {
+            meta.depth = results.iter().map(|result| result.depth).collect();
+            meta.depth.sort_unstable();
+            meta.depth.dedup();
+        }
+
+        meta
+    }
+
+    /// Spawn `--jobs` engines, all configured identically, but never more 
+    /// than there are searches to run
+    fn start_engines(
+        &self, 
+        path: &Path, 
+        meta: &Metadata, 
+        searches: usize
+    ) -> anyhow::Result<Vec<Engine>> {
+        let jobs = (self.jobs as usize).min(searches).max(1);
+
+        (0..jobs).map(|_| self.start_engine(path, meta)).collect()
+    }
+
+    /// Spawn the engine and configure it with the requested settings
+    fn start_engine(&self, path: &Path, meta: &Metadata) -> anyhow::Result<Engine> {
+        let mut engine = Engine::new(
+            path, 
+            &meta.engine_args, 
+            self.engine_cwd.as_deref(), 
+            self.engine_log.as_deref()
+        )?;
+
+        if let Some(hash) = meta.hash {
+            set_engine_option(&mut engine, "Hash", &hash.to_string())?;
+        }
+
+        if let Some(threads) = meta.threads {
+            set_engine_option(&mut engine, "Threads", &threads.to_string())?;
+        }
+
+        if meta.chess960 {
+            set_engine_option(&mut engine, "UCI_Chess960", "true")?;
+        }
+
+        if let Some(syzygy) = &meta.syzygy {
+            check_syzygy_path(syzygy)?;
+            set_engine_option(&mut engine, "SyzygyPath", syzygy)?;
+        }
+
+        if let Some(eval_file) = &meta.eval_file {
+            set_engine_option(&mut engine, "EvalFile", eval_file)?;
+        }
+
+        for option in &meta.options {
+            match &option.value {
+                Some(value) => set_engine_option(&mut engine, &option.name, value)?,
+                None => engine.press_button(&option.name)?,
+            }
+        }
+
+        if self.multipv > 1 {
+            set_engine_option(&mut engine, "MultiPV", &self.multipv.to_string())?;
+        }
+
+        if meta.shared_hash {
+            engine.share_hash();
+        }
+
+        if self.record_progress {
+            engine.record_progress();
+        }
+
+        if let Some(depth) = meta.target_depth {
+            engine.set_target_depth(depth);
+        }
+
+        Ok(engine)
+    }
+
+    /// Pair every engine with the network it should evaluate with. A single
+    /// `--eval-file` applies to every engine, several networks for a single
+    /// engine each get a run of their own, and otherwise the networks are 
+    /// paired with the engines in order. Without `--eval-file`, the engines
+    /// use whatever network the settings say.
+    fn runs<'a>(&self, engines: &[&'a Path]) -> anyhow::Result<Vec<(&'a Path, Option<String>)>> {
+        let nets = &self.eval_file;
+
+        let runs = match (engines.len(), nets.len()) {
+            (_, 0) => engines.iter().map(|&engine| (engine, None)).collect(),
+            (_, 1) => engines.iter().map(|&engine| (engine, Some(nets[0].clone()))).collect(),
+            (1, _) => nets.iter().map(|net| (engines[0], Some(net.clone()))).collect(),
+            (engine_count, net_count) if engine_count == net_count => engines.iter()
+                .zip(nets)
+                .map(|(&engine, net)| (engine, Some(net.clone())))
+                .collect(),
+
+            (engine_count, net_count) => Err(anyhow!(
+                "Got {net_count} eval files for {engine_count} engines. Pass a single --eval-file, or one for every engine"
+            ))?,
+        };
+
+        Ok(runs)
+    }
+
+    /// The settings to store alongside the results in a snapshot
+    fn metadata(&self) -> Metadata {
+        Metadata {
+            engine: None,
+            timestamp: None,
+            depth: Vec::new(),
+            hash: self.hash,
+            threads: self.threads,
+            movetime: self.movetime,
+            max_nodes: self.max_nodes,
+            syzygy: self.syzygy.clone(),
+            eval_file: self.eval_file.first().cloned(),
+            chess960: self.chess960,
+            shared_hash: self.shared_hash,
+            target_depth: self.target_depth,
+            seed: self.seed,
+            options: self.options.clone(),
+            engine_args: self.engine_arg.clone(),
+        }
+    }
+
+    /// Use the settings stored in a snapshot for anything that wasn't passed
+    /// explicitly, so the comparison is fair. Warns about any settings that 
+    /// differ from the snapshot.
+    fn inherit_metadata(&self, snapshot: &Metadata) -> Metadata {
+        Metadata {
+            engine: None,
+            timestamp: None,
+            depth: self.inherit_depth(&snapshot.depth),
+            hash: inherit_setting("hash", self.hash, snapshot.hash),
+            threads: inherit_setting("threads", self.threads, snapshot.threads),
+
+            // Only one search limit can apply, so an explicit one replaces 
+            // whatever the snapshot used
+            movetime: match self.max_nodes {
+                Some(_) => None,
+                None => inherit_setting("movetime", self.movetime, snapshot.movetime),
+            },
+
+            max_nodes: match self.movetime {
+                Some(_) => None,
+                None => inherit_setting("max-nodes", self.max_nodes, snapshot.max_nodes),
+            },
+
+            syzygy: inherit_setting("syzygy", self.syzygy.clone(), snapshot.syzygy.clone()),
+            eval_file: inherit_setting(
+                "eval file", 
+                self.eval_file.first().cloned(), 
+                snapshot.eval_file.clone()
+            ),
+            chess960: self.chess960 || snapshot.chess960,
+            shared_hash: self.shared_hash || snapshot.shared_hash,
+            target_depth: inherit_setting("target depth", self.target_depth, snapshot.target_depth),
+
+            // The order of the positions doesn't affect the comparison
+            seed: self.seed,
+            options: inherit_list("options", &self.options, &snapshot.options),
+            engine_args: inherit_list("engine args", &self.engine_arg, &snapshot.engine_args),
+        }
+    }
+
+    /// Run every search, given as a FEN and a depth, spreading them over the
+    /// engines. Every engine runs on its own thread, and picks up the next 
+    /// search as soon as it's done with the previous one.
+    ///
+    /// `on_result` is called with the index and result of every search, in
+    /// the original order, as soon as that search and all of the ones before
+    /// it are done. The result is `None` when the engine crashed or timed out
+    /// on that position.
+    fn search_all(
+        &self,
+        engines: &mut [Engine],
+        meta: &Metadata,
+        searches: &[(&str, usize, &[String])],
+        mut on_result: impl FnMut(usize, Option<SearchResult>) -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        let next = AtomicUsize::new(0);
+        let restarts = AtomicUsize::new(0);
+        let (sender, receiver) = mpsc::channel();
+
+        thread::scope(|scope| {
+            for engine in engines.iter_mut() {
+                let sender = sender.clone();
+                let next = &next;
+                let restarts = &restarts;
+
+                scope.spawn(move || loop {
+                    let idx = next.fetch_add(1, Ordering::Relaxed);
+
+                    let Some(&(fen, depth, searchmoves)) = searches.get(idx) else {
+                        break;
+                    };
+
+                    let result = self.search(engine, meta, fen, depth, searchmoves, restarts);
+
+                    // Searches that got cut short by Ctrl-C are thrown away
+                    if interrupt::interrupted() {
+                        break;
+                    }
+
+                    // The receiver is gone once the run has errored out
+                    if sender.send((idx, result)).is_err() {
+                        break;
+                    }
+                });
+            }
+
+            drop(sender);
+
+            // Searches can finish out of order, so hold on to any results 
+            // until all of the earlier ones are in
+            let mut pending = HashMap::new();
+            let mut next_idx = 0;
+
+            for (idx, result) in receiver {
+                pending.insert(idx, result?);
+
+                while let Some(result) = pending.remove(&next_idx) {
+                    on_result(next_idx, result)?;
+                    next_idx += 1;
+                }
+            }
+
+            Ok(())
+        })
+    }
+
+    /// Search a single position `--warmup` times without recording anything,
+    /// and then `--repeat` times, and aggregate those runs into a single 
+    /// result.
+    ///
+    /// Returns `None` when the engine crashed or timed out on this position.
+    /// A crashed engine is replaced by a freshly started one.
+    fn search(
+        &self, 
+        engine: &mut Engine, 
+        meta: &Metadata, 
+        fen: &str, 
+        depth: usize,
+        searchmoves: &[String],
+        restarts: &AtomicUsize,
+    ) -> anyhow::Result<Option<SearchResult>> {
+        for _ in 0..self.warmup {
+            if self.search_once(engine, meta, fen, depth, searchmoves, restarts)?.is_none() {
+                return Ok(None);
+            }
+        }
+
+        let mut runs = Vec::new();
+
+        for _ in 0..self.repeat {
+            match self.search_once(engine, meta, fen, depth, searchmoves, restarts)? {
+                Some(result) => runs.push(result),
+                None => return Ok(None),
+            }
+        }
+
+        let mut nondeterministic = false;
+
+        if self.check_determinism {
+            let mut check = Vec::new();
+
+            // Without repeats, there's nothing to compare against, so search
+            // once more, without counting the extra search towards the result
+            if runs.len() < 2 {
+                match self.search_once(engine, meta, fen, depth, searchmoves, restarts)? {
+                    Some(result) => check.push(result),
+                    None => return Ok(None),
+                }
+            }
+
+            let first = &runs[0];
+            let mismatch = runs[1..].iter().chain(&check).find(|run| {
+                run.nodes != first.nodes || run.best_move != first.best_move
+            });
+
+            if let Some(other) = mismatch {
+                nondeterministic = true;
+
+                eprintln!(
+                    "{} non-deterministic search of {fen}: {} ({}) vs. {} ({})",
+                    "Warning:".yellow(),
+                    first.nodes,
+                    first.best_move,
+                    other.nodes,
+                    other.best_move,
+                );
+            }
+        }
+
+        let mut result = SearchResult::aggregate(runs);
+        result.nondeterministic = nondeterministic;
+
+        Ok(Some(result))
+    }
+
+    /// Search a single position once, restarting the engine if it crashes. 
+    /// Returns `None` when the engine crashed, or when it timed out, in which
+    /// case the position is reported as failed.
+    fn search_once(
+        &self, 
+        engine: &mut Engine, 
+        meta: &Metadata, 
+        fen: &str, 
+        depth: usize,
+        searchmoves: &[String],
+        restarts: &AtomicUsize,
+    ) -> anyhow::Result<Option<SearchResult>> {
+        let board = fen.parse()?;
+
+        match engine.search(board, time_control(meta, depth), searchmoves, self.timeout()) {
+            Ok(result) => Ok(Some(result)),
+
+            Err(err) if !engine.is_alive() => {
+                if restarts.fetch_add(1, Ordering::Relaxed) >= self.max_restarts {
+                    return Err(err.context(format!(
+                        "Giving up after {} engine restarts", 
+                        self.max_restarts
+                    )));
+                }
+
+                eprintln!("{} {err}, restarting the engine", "Warning:".yellow());
+                *engine = self.start_engine(engine.path(), meta)?;
+
+                Ok(None)
+            },
+
+            Err(err) if err.is::<SearchTimeout>() => {
+                eprintln!("{} {err}, skipping the position", "Warning:".yellow());
+                Ok(None)
+            },
+
+            Err(err) => Err(err),
+        }
+    }
+
+    /// The depths to search every position at. Searching by time or nodes
+    /// ignores the depth, so those only search each position once. When 
+    /// comparing against a snapshot, positions that aren't in it are searched
+    /// at the depths the snapshot was taken at.
+    fn depths(&self, meta: &Metadata) -> Vec<usize> {
+        let depth = match self.depth.as_slice() {
+            [] => vec![DEFAULT_DEPTH],
+            depth => depth.to_vec(),
+        };
+
+        if meta.movetime.is_some() || meta.max_nodes.is_some() {
+            depth[..1].to_vec()
+        } else if !meta.depth.is_empty() {
+            meta.depth.clone()
+        } else {
+            depth
+        }
+    }
+
+    /// With `--re-depth`, the depths the positions in the snapshot get 
+    /// searched at instead of the ones they were searched at before, matched
+    /// up in order. Returns `None` when the depths are the same, or when 
+    /// searching by time or nodes.
+    fn redepth(&self, snapshot: &[SearchResult], meta: &Metadata) -> anyhow::Result<Option<Redepth>> {
+        if !self.re_depth || meta.movetime.is_some() || meta.max_nodes.is_some() {
+            return Ok(None);
+        }
+
+        let mut from = snapshot.iter().map(|result| result.depth).collect::<Vec<_>>();
+        from.sort_unstable();
+        from.dedup();
+
+        let mut to = self.depth.clone();
+        to.sort_unstable();
+        to.dedup();
+
+        if from == to {
+            return Ok(None);
+        }
+
+        if from.len() != to.len() {
+            return Err(anyhow!(
+                "The snapshot was searched at depth {}, so --re-depth needs {} depth(s), but got --depth {}",
+                depth_list(&from),
+                from.len(),
+                depth_list(&to),
+            ));
+        }
+
+        Ok(Some((from, to)))
+    }
+
+    /// The depths to search at when comparing against a snapshot: the ones 
+    /// the snapshot was taken at, so the comparison is like for like, unless
+    /// `--re-depth` was passed. Warns when `--depth` asks for other depths.
+    fn inherit_depth(&self, snapshot: &[usize]) -> Vec<usize> {
+        let mut depth = self.depth.clone();
+        depth.sort_unstable();
+        depth.dedup();
+
+        if depth.is_empty() || snapshot.is_empty() || depth == snapshot {
+            return snapshot.to_vec();
+        }
+
+        if self.re_depth {
+            eprintln!(
+                "{} searching at depth {}, but the snapshot was taken at depth {}, so the comparison is between different depths",
+                "Warning:".yellow(),
+                depth_list(&depth),
+                depth_list(snapshot),
+            );
+
+            return depth;
+        }
+
+        eprintln!(
+            "{} ignoring --depth {}, and searching at depth {} like the snapshot. Pass --re-depth to search at depth {} instead",
+            "Warning:".yellow(),
+            depth_list(&depth),
+            depth_list(snapshot),
+            depth_list(&depth),
+        );
+
+        snapshot.to_vec()
+    }
+
+    /// Whether to hold off on printing the rows until all of the positions 
+    /// were searched, because they need to be sorted, or checked for outliers
+    fn defer_rows(&self) -> bool {
+        self.sort_by.is_some() || self.drop_outliers.is_some()
+    }
+
+    /// Which of the results have an outlying search time, according to 
+    /// `--drop-outliers`. Nothing is flagged without the flag.
+    fn outliers(&self, results: &[SearchResult]) -> Vec<bool> {
+        let Some(rule) = self.drop_outliers else {
+            return vec![false; results.len()];
+        };
+
+        let times = results.iter()
+            .map(|result| result.time.0 as f64)
+            .collect::<Vec<_>>();
+
+        stats::outliers(&times, rule)
+    }
+
+    /// The order to print the rows in, as indices into the results. Keeps the
+    /// original order unless `--sort-by` was passed.
+    fn sort_order(&self, len: usize, value: impl Fn(usize, SortKey) -> f32) -> Vec<usize> {
+        let mut order = (0..len).collect::<Vec<_>>();
+
+        if let Some(key) = self.sort_by {
+            order.sort_by(|&a, &b| {
+                let ordering = value(a, key).total_cmp(&value(b, key));
+                if self.sort_desc { ordering.reverse() } else { ordering }
+            });
+        }
+
+        order
+    }
+
+    /// Write the JUnit report to the file passed with `--junit`, if any. The
+    /// regressions are the percentages by which the gate metric of every 
+    /// result regressed, when compared against a snapshot.
+    fn write_junit(
+        &self, 
+        results: &[SearchResult], 
+        regressions: &[Option<f32>],
+        failed: &[String]
+    ) -> anyhow::Result<()> {
+        let Some(path) = &self.junit else {
+            return Ok(());
+        };
+
+        let xml = junit::to_junit(
+            results, 
+            regressions, 
+            self.gate_metric, 
+            self.fail_on_regression, 
+            failed
+        );
+
+        write(path, xml)
+            .map_err(|err| anyhow!("Failed to write JUnit report to {}: {err}", path.display()))
+    }
+
+    /// A progress bar that advances once per search, drawn to stderr so it 
+    /// doesn't end up in the table or CSV output. Hidden when stdout isn't a
+    /// terminal, since nobody is watching the run in that case.
+    fn progress_bar(&self, len: usize) -> ProgressBar {
+        // The progress bar would get mixed up with the UCI echo
+        if !std::io::stdout().is_terminal() || self.verbose > 0 {
+            return ProgressBar::hidden();
+        }
+
+        let style = ProgressStyle::with_template(
+            "{bar:40.cyan/blue} {pos}/{len} positions [{elapsed_precise}, ETA {eta}]"
+        ).expect("the progress bar template is valid");
+
+        ProgressBar::with_draw_target(Some(len as u64), ProgressDrawTarget::stderr())
+            .with_style(style)
+    }
+
+    /// The per-position search timeout, if any
+    fn timeout(&self) -> Option<Duration> {
+        self.timeout.map(Duration::from_millis)
+    }
+
+    /// Load the suite of positions to run, removing duplicates, applying the
+    /// filter, and keeping only the positions selected with `--skip` and 
+    /// `--limit`.
+    fn load_suite(&self) -> anyhow::Result<Vec<Epd>> {
+        Ok(self.shuffle(self.window(self.load_whole_suite()?)))
+    }
+
+    /// Shuffle the positions with the seed, if requested
+    fn shuffle<T>(&self, mut positions: Vec<T>) -> Vec<T> {
+        if let Some(seed) = self.seed {
+            positions.shuffle(&mut StdRng::seed_from_u64(seed));
+        }
+
+        positions
+    }
+
+    /// Shuffle the results with the seed, if requested, keeping the results 
+    /// of every position together.
+    fn shuffle_results(&self, results: Vec<SearchResult>) -> Vec<SearchResult> {
+        self.shuffle(by_position(results)).concat()
+    }
+
+    /// Keep only the positions selected with `--skip` and `--limit`
+    fn window<T>(&self, positions: Vec<T>) -> Vec<T> {
+        let limit = self.limit.map_or(usize::MAX, |limit| limit as usize);
+        positions.into_iter().skip(self.skip).take(limit).collect()
+    }
+
+    /// Keep only the results of the positions selected with `--skip` and 
+    /// `--limit`. A 
+    /// position is in a snapshot once for every depth it was searched at, 
+    /// so those count as one.
+    fn window_results(&self, results: Vec<SearchResult>) -> Vec<SearchResult> {
+        self.window(by_position(results)).concat()
+    }
+
+    /// Load the suite of positions, removing duplicates and applying the 
+    /// filter if requested, but ignoring `--skip` and `--limit`.
+    fn load_whole_suite(&self) -> anyhow::Result<Vec<Epd>> {
+        let mut suite = self.read_suite()?;
+
+        if self.dedupe {
+            let mut seen = HashSet::new();
+            let total = suite.len();
+
+            suite.retain(|epd| seen.insert(normalize_fen(&epd.fen, self.ignore_counters)));
+
+            if suite.len() < total {
+                eprintln!("Dropped {} duplicate position(s)", total - suite.len());
+            }
+        }
+
+        suite.retain(|epd| self.matches_filter(epd.id().as_deref()));
+
+        if suite.is_empty() && self.filter.is_some() {
+            return Err(anyhow!("No positions in the suite match the filter"));
+        }
+
+        let total = suite.len();
+        let suite = self.filter_pieces(suite, |epd| &epd.fen);
+
+        if suite.len() < total {
+            eprintln!(
+                "Dropped {} of {total} position(s) outside of the requested number of pieces",
+                total - suite.len(),
+            );
+        }
+
+        if suite.is_empty() {
+            return Err(anyhow!("No positions in the suite have the requested number of pieces"));
+        }
+
+        Ok(suite)
+    }
+
+    /// Match the results in the snapshot to the positions in the suite by 
+    /// their FEN, so changes to the order of the suite don't throw off the 
+    /// comparison.
+    ///
+    /// Returns the snapshot results to compare against, in the order of the 
+    /// suite, along with the positions that aren't in the snapshot, and the 
+    /// snapshot results that aren't in the suite.
+    fn match_suite(
+        &self, 
+        snapshot: Vec<SearchResult>, 
+        suite: &[Epd]
+    ) -> anyhow::Result<(Vec<SearchResult>, Vec<Epd>, Vec<SearchResult>)> {
+        // A position is in the snapshot once for every depth it was searched
+        // at, right after each other. Group those, so every occurrence of a
+        // position in the suite gets matched to one group.
+        let mut baselines: HashMap<String, VecDeque<Vec<SearchResult>>> = HashMap::new();
+        let mut keys: Vec<String> = Vec::new();
+
+        for result in snapshot {
+            let key = normalize_fen(&result.position, self.ignore_counters);
+            let groups = baselines.entry(key.clone()).or_default();
+
+            let same_group = keys.last() == Some(&key) && groups.back()
+                .is_some_and(|group| group.iter().all(|other| other.depth != result.depth));
+
+            match groups.back_mut() {
+                Some(group) if same_group => group.push(result),
+                _ => {
+                    groups.push_back(vec![result]);
+                    keys.push(key);
+                }
+            }
+        }
+
+        let mut matched = Vec::new();
+        let mut new = Vec::new();
+
+        for epd in suite {
+            // Results hold the FEN as the engine was given it
+            let fen = epd.fen.parse::<Board>()?.to_fen();
+            let key = normalize_fen(&fen, self.ignore_counters);
+
+            let Some(results) = baselines.get_mut(&key).and_then(VecDeque::pop_front) else {
+                new.push(epd.clone());
+                continue;
+            };
+
+            for mut result in results {
+                // The suite has the final say on the annotations
+                if epd.id().is_some() {
+                    result.id = epd.id();
+                }
+
+                if !epd.best_moves().is_empty() {
+                    result.bm = epd.best_moves();
+                }
+
+                matched.push(result);
+            }
+        }
+
+        // Whatever wasn't matched is gone from the suite, in snapshot order
+        let gone = keys.iter()
+            .filter_map(|key| baselines.get_mut(key).and_then(VecDeque::pop_front))
+            .flatten()
+            .collect();
+
+        Ok((matched, new, gone))
+    }
+
+    /// Drop the positions from the suite that were already searched at every
+    /// depth in an earlier run, and return the results of those searches. 
+    /// Positions that only got searched at some of the depths are searched 
+    /// again.
+    fn skip_done(
+        &self, 
+        suite: &mut Vec<Epd>, 
+        done: Vec<SearchResult>, 
+        meta: &Metadata
+    ) -> anyhow::Result<Vec<SearchResult>> {
+        let depths = self.depths(meta).len();
+        let mut searches: HashMap<String, usize> = HashMap::new();
+
+        for result in &done {
+            *searches.entry(normalize_fen(&result.position, false)).or_default() += 1;
+        }
+
+        let mut remaining = HashSet::new();
+
+        for epd in suite.iter() {
+            // Results hold the FEN as the engine was given it
+            let key = normalize_fen(&epd.fen.parse::<Board>()?.to_fen(), false);
+
+            if searches.get(&key).copied().unwrap_or_default() < depths {
+                remaining.insert(key);
+            }
+        }
+
+        suite.retain(|epd| {
+            let fen = epd.fen.parse::<Board>().map(|board| board.to_fen());
+            fen.is_ok_and(|fen| remaining.contains(&normalize_fen(&fen, false)))
+        });
+
+        Ok(done.into_iter()
+            .filter(|result| !remaining.contains(&normalize_fen(&result.position, false)))
+            .collect())
+    }
+
+    /// Save the results so far to the output file, so the run can be resumed
+    /// with `--resume` when it gets interrupted. The snapshot is written to a
+    /// temporary file first, so an interruption while saving doesn't leave a
+    /// broken snapshot behind.
+    fn checkpoint(&self, meta: &Metadata, results: &[SearchResult]) -> anyhow::Result<()> {
+        let snapshot = Snapshot::new(self.stamp(meta.clone(), results), results.to_vec());
+        let partial = self.output.with_extension("partial");
+
+        write(&partial, serde_json::to_string(&snapshot)?)?;
+        std::fs::rename(&partial, &self.output)?;
+
+        Ok(())
+    }
+
+    /// Without a suite, the positions in the snapshot are the ones that get 
+    /// compared. When the snapshot holds only some of the default positions,
+    /// e.g., because the run that saved it was interrupted, the missing ones
+    /// are searched as well, as new positions.
+    ///
+    /// Returns the same as `match_suite`.
+    fn complete_snapshot(
+        &self, 
+        snapshot: Vec<SearchResult>
+    ) -> anyhow::Result<(Vec<SearchResult>, Vec<Epd>, Vec<SearchResult>)> {
+        // The default positions aren't labeled, so they never pass a filter
+        let Ok(suite) = self.load_suite() else {
+            return Ok((self.shuffle_results(snapshot), Vec::new(), Vec::new()));
+        };
+
+        let (matched, new, gone) = self.match_suite(snapshot.clone(), &suite)?;
+
+        // A snapshot of another suite has positions the default suite doesn't
+        if matched.is_empty() || !gone.is_empty() {
+            return Ok((self.shuffle_results(snapshot), Vec::new(), Vec::new()));
+        }
+
+        Ok((matched, new, gone))
+    }
+
+    /// Match the snapshot to the positions in the suite that were selected 
+    /// with `--skip` and `--limit`. Positions that are in the suite, but weren't selected,
+    /// don't count as gone.
+    fn match_window(
+        &self, 
+        snapshot: Vec<SearchResult>
+    ) -> anyhow::Result<(Vec<SearchResult>, Vec<Epd>, Vec<SearchResult>)> {
+        let suite = self.load_whole_suite()?;
+        let selected = self.shuffle(self.window(suite.clone()));
+        let (matched, new, gone) = self.match_suite(snapshot, &selected)?;
+        let (_, _, gone) = self.match_suite(gone, &suite)?;
+
+        Ok((matched, new, gone))
+    }
+
+    /// Drop the positions with fewer pieces than `--min-pieces`, or more than
+    /// `--max-pieces`
+    fn filter_pieces<T>(&self, positions: Vec<T>, fen: impl Fn(&T) -> &str) -> Vec<T> {
+        if self.min_pieces.is_none() && self.max_pieces.is_none() {
+            return positions;
+        }
+
+        let min = self.min_pieces.unwrap_or(0);
+        let max = self.max_pieces.unwrap_or(u32::MAX);
+
+        positions.into_iter()
+            .filter(|position| parse_fen(fen(position))
+                .is_ok_and(|board| (min..=max).contains(&board.all_occupied().count()))
+            )
+            .collect()
+    }
+
+    /// Check whether a position with the given label passes `--filter`. The
+    /// match is case-insensitive, and unlabeled positions never match.
+    fn matches_filter(&self, label: Option<&str>) -> bool {
+        let Some(filter) = &self.filter else {
+            return true;
+        };
+
+        label.is_some_and(|label| label.to_lowercase().contains(&filter.to_lowercase()))
+    }
+
+    /// Read the positions from the suite file, or fall back to the default 
+    /// positions.
+    ///
+    /// Every position is validated up front, so a bad line doesn't abort the
+    /// run halfway through. All invalid lines are reported at once, or 
+    /// skipped when `--skip-invalid` is passed.
+    fn read_suite(&self) -> anyhow::Result<Vec<Epd>> {
+        if let Some(file) = &self.pgn {
+            return self.read_pgn(file);
+        }
+
+        let Some(file) = &self.fens else {
+            return Ok(POSITIONS.into_iter().map(Epd::from_fen).collect());
+        };
+
+        let is_epd = file.extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("epd"));
+
+        let contents = std::fs::read_to_string(file)
+            .map_err(|err| anyhow!("Failed to read {}: {err}", file.display()))?;
+
+        let mut suite = Vec::new();
+        let mut errors = Vec::new();
+
+        for (idx, line) in contents.lines().enumerate() {
+            let line = line.trim();
+
+            if is_comment(line) {
+                continue;
+            }
+
+            let epd = if is_epd { line.parse() } else { Ok(Epd::from_line(line)) };
+            let epd = epd.and_then(|mut epd: Epd| {
+                // Positions are stored in X-FEN, so they can be parsed and
+                // matched against snapshots like any other position
+                if self.chess960 {
+                    epd.fen = to_xfen(&epd.fen)?;
+                }
+
+                parse_fen(&epd.fen).map(|_| epd)
+            });
+
+            match epd {
+                Ok(epd) => suite.push(epd),
+                Err(err) => errors.push(format!("line {}: '{line}' ({err})", idx + 1)),
+            }
+        }
+
+        if !errors.is_empty() && !self.skip_invalid {
+            return Err(anyhow!(
+                "Found {} invalid position(s) in {}:\n  {}\nPass --skip-invalid to skip them",
+                errors.len(),
+                file.display(),
+                errors.join("\n  ")
+            ));
+        }
+
+        for error in errors {
+            eprintln!("{} skipping invalid position on {error}", "Warning:".yellow());
+        }
+
+        Ok(suite)
+    }
+
+    /// Sample the suite from the games in a PGN file. Games that can't be 
+    /// replayed (e.g., because of an illegal move, or a broken `FEN` tag) are
+    /// skipped with a warning, so a single bad game doesn't sink a whole 
+    /// database.
+    fn read_pgn(&self, file: &Path) -> anyhow::Result<Vec<Epd>> {
+        let contents = std::fs::read_to_string(file)
+            .map_err(|err| anyhow!("Failed to read {}: {err}", file.display()))?;
+
+        let games = pgn::parse_games(&contents);
+        let mut suite = Vec::new();
+        let mut skipped = 0;
+
+        for (idx, game) in games.iter().enumerate() {
+            let number = idx + 1;
+
+            match game.sample(number, self.sample_every as usize, self.chess960) {
+                Ok(positions) => suite.extend(positions),
+                Err(err) => {
+                    eprintln!("{} skipping game {number} ({err})", "Warning:".yellow());
+                    skipped += 1;
+                }
+            }
+        }
+
+        if suite.is_empty() {
+            return Err(anyhow!(
+                "No positions could be sampled from the {} game(s) in {}",
+                games.len(),
+                file.display()
+            ));
+        }
+
+        eprintln!(
+            "Sampled {} position(s) from {} of {} game(s) in {}",
+            suite.len(),
+            games.len() - skipped,
+            games.len(),
+            file.display()
+        );
+
+        Ok(suite)
+    }
+
+    /// Run the engine against a snapshot of SearchResults and return the
+    /// Vec of new SearchResults, along with the averaged diff.
+    ///
+    /// Also responsible for reporting/printing the results as they come in.
+    fn run_snapshot(
+        &self, 
+        engine: &Path,
+        snapshot: &[SearchResult], 
+        new: &[Epd],
+        gone: &[SearchResult],
+        meta: &mut Metadata
+    ) -> anyhow::Result<(Vec<SearchResult>, Diff)> {
+        let start = Instant::now();
+        let mut results = Vec::new();
+        let mut baselines = Vec::new();
+        let mut diffs = Vec::new();
+        let mut added = Vec::new();
+        let mut failed = Vec::new();
+
+        // Positions that aren't in the snapshot get searched after the ones 
+        // that are, but can't be compared
+        let depths = self.depths(meta);
+
+        let searchmoves = new.iter().map(Epd::search_moves).collect::<Vec<_>>();
+        let redepth = self.redepth(snapshot, meta)?;
+
+        let searches = snapshot.iter()
+            .map(|result| {
+                let depth = redepth.as_ref()
+                    .and_then(|(from, to)| from.iter()
+                        .position(|&depth| depth == result.depth)
+                        .map(|idx| to[idx])
+                    )
+                    .unwrap_or(result.depth);
+
+                (result.position.as_str(), depth, result.searchmoves.as_slice())
+            })
+            .chain(suite_searches(new, &searchmoves, &depths))
+            .collect::<Vec<_>>();
+
+        let mut engines = self.start_engines(engine, meta, searches.len())?;
+        meta.engine = Some(engines[0].id().clone());
+
+        let mut fields = Fields::from(self);
+        fields.label = snapshot.iter().chain(gone).any(|result| result.id.is_some())
+            || new.iter().any(|epd| epd.id().is_some());
+        fields.depth = searches.iter().any(|&(_, depth, _)| depth != searches[0].1);
+
+        let mut report = self.new_report()?;
+
+        report.add_col("FEN", 72).truncate();
+
+        if fields.label {
+            report.add_col("Label", 15).align(Alignment::Left).truncate();
+        }
+
+        if fields.depth {
+            report.add_col("Depth", 5);
+        }
+
+        if fields.nodes {
+            report.add_col("Nodes", 45);
+        }
+
+        if fields.time {
+            report.add_col("Time", 30);
+        }
+
+        if fields.nps {
+            report.add_col("Nps", 30);
+        }
+
+        if fields.branching {
+            report.add_col("Branching Factor", 25);
+        }
+
+        if fields.ebf {
+            report.add_col("EBF", 25);
+        }
+
+        if fields.reached_depth {
+            report.add_col("Reached Depth", 25);
+        }
+
+        if fields.seldepth {
+            report.add_col("Seldepth", 25);
+        }
+
+        if fields.hashfull {
+            report.add_col("Hashfull", 31);
+        }
+
+        if fields.tbhits {
+            report.add_col("TB hits", 35);
+        }
+
+        if let Some(depth) = self.target_depth.filter(|_| fields.time_to_depth) {
+            report.add_col(&format!("Time to depth {depth}"), 30);
+        }
+
+        if fields.score {
+            report.add_col("Score", 15);
+        }
+
+        if fields.best_move {
+            report.add_col("Best Move", 15);
+        }
+
+        if fields.pv {
+            report.add_col("PV", 60);
+        }
+
+        print_engine(&report, &engines[0]);
+        report.print_header();
+
+        let progress = self.progress_bar(searches.len());
+
+        self.search_all(&mut engines, meta, &searches, |idx, result| {
+            let fen = searches[idx].0;
+            progress.inc(1);
+
+            let Some(mut result) = result else {
+                if !self.quiet {
+                    progress.suspend(|| report.print_row(&failed_row(&report, fen)));
+                }
+
+                failed.push(fen.to_string());
+                return Ok(());
+            };
+
+            let Some(snapshot_result) = snapshot.get(idx) else {
+                let epd = &new[(idx - snapshot.len()) / depths.len()];
+                result.id = epd.id();
+                result.bm = epd.best_moves();
+
+                if !self.defer_rows() && !self.quiet {
+                    progress.suspend(|| report.print_row(&unmatched_row(&result, &fields, "NEW")));
+                }
+
+                added.push(result);
+                return Ok(());
+            };
+
+            // Carry over the EPD annotations, so they survive re-saving
+            result.id = snapshot_result.id.clone();
+            result.bm = snapshot_result.bm.clone();
+
+            let diff = Diff::new(snapshot_result, &result);
+
+            if diff.reached_depth.changed() {
+                progress.suspend(|| eprintln!(
+                    "{} the engine reached depth {} on {fen}, but depth {} in the snapshot",
+                    "Warning:".yellow(),
+                    diff.reached_depth.second,
+                    diff.reached_depth.first,
+                ));
+            }
+
+            // Print the diff as a row of the report, unless the rows need to
+            // be sorted first
+            if !self.defer_rows() && !self.quiet {
+                progress.suspend(|| print_diff(&report, &diff, &result, &fields, false));
+            }
+
+            // Store the result
+            results.push(result);
+            baselines.push(snapshot_result.clone());
+            diffs.push(diff);
+
+            Ok(())
+        })?;
+
+        progress.finish_and_clear();
+
+        let order = self.sort_order(diffs.len(), |i, key| {
+            diffs[i].sort_value(key, self.sort_relative)
+        });
+
+        let outliers = self.outliers(&results);
+
+        if self.defer_rows() && !self.quiet {
+            for &i in &order {
+                print_diff(&report, &diffs[i], &results[i], &fields, outliers[i]);
+            }
+
+            for result in &added {
+                report.print_row(&unmatched_row(result, &fields, "NEW"));
+            }
+        }
+
+        if !self.quiet {
+            for result in gone {
+                report.print_row(&unmatched_row(result, &fields, "GONE"));
+            }
+        }
+
+        if self.format == Format::Json {
+            let sorted = order.iter().map(|&i| &diffs[i]).collect::<Vec<_>>();
+            println!("{}", serde_json::to_string_pretty(&sorted)?);
+        }
+
+        if self.format == Format::Github {
+            for &i in &order {
+                let annotation = annotation(
+                    &diffs[i], 
+                    self.gate_metric, 
+                    self.warn_on_regression, 
+                    self.fail_on_regression
+                );
+
+                if let Some(annotation) = annotation {
+                    println!("{annotation}");
+                }
+            }
+        }
+
+        let mut regressions = diffs.iter()
+            .map(|diff| diff.regression(self.gate_metric).map(|regression| 100.0 * regression))
+            .collect::<Vec<_>>();
+
+        // Print averages, potentially behind a flag. The header already ends 
+        // in a separator when there are no rows.
+        if !self.quiet {
+            report.print_separator();
+        }
+
+        // Outliers are left out of the summary on both sides, so the 
+        // comparison stays paired
+        let kept_diffs = without_outliers(&diffs, &outliers);
+        let kept_baselines = without_outliers(&baselines, &outliers);
+        let kept_results = without_outliers(&results, &outliers);
+
+        let averages = match self.aggregate {
+            Aggregate::Mean => {
+                let mut averages = kept_diffs.iter().cloned().sum::<Diff>() / kept_results.len().max(1);
+
+                // Branching factors are ratios, so compare their geometric
+                // means instead
+                let baseline_summary = SearchResult::summarize(&kept_baselines, Aggregate::Mean);
+                let summary = SearchResult::summarize(&kept_results, Aggregate::Mean);
+
+                averages.branching_factor = BFactorDiff::new(
+                    baseline_summary.branching_factor,
+                    summary.branching_factor,
+                );
+
+                averages.ebf = BFactorDiff::new(baseline_summary.ebf, summary.ebf);
+                averages.time_to_depth = TimeToDepthDiff::new(
+                    baseline_summary.time_to_depth,
+                    summary.time_to_depth,
+                );
+
+                // Compare the nps over the entire suite, rather than the 
+                // average of the per-position changes
+                averages.nps = NpsDiff::new(
+                    SearchResult::total_nps(&kept_baselines),
+                    SearchResult::total_nps(&kept_results),
+                );
+
+                averages
+            },
+
+            // The relative change of the medians, rather than the median of 
+            // the relative changes
+            Aggregate::Median => Diff::new(
+                &SearchResult::summarize(&kept_baselines, Aggregate::Median),
+                &SearchResult::summarize(&kept_results, Aggregate::Median),
+            ),
+        };
+
+        report.print_row(&averages.extract(&fields));
+
+        // Print footer line
+        report.print_footer();
+        print_failed(&report, &failed);
+
+        if let Some(rule) = self.drop_outliers {
+            print_outliers(&report, &outliers, rule);
+        }
+
+        print_verdict(&report, &averages, self.gate_metric, redepth.as_ref());
+        print_percentiles(&report, &diffs);
+
+        if self.significance {
+            print_significance(&report, &diffs, self.alpha);
+        }
+        print_unmatched(&report, added.len(), gone.len());
+
+        if self.format == Format::Github {
+            let summary = verdict(&averages, self.gate_metric, redepth.as_ref())
+                .unwrap_or_else(|| "No change to compare".to_string());
+
+            let summary = format!("{summary} over {} position(s)", results.len());
+            println!("{}", report::workflow_command("notice", "chess-bench", &summary));
+        }
+
+        // New positions are saved along with the rest, but don't count 
+        // towards the comparison
+        results.extend(added);
+        regressions.resize(results.len(), None);
+
+        print_solved(&report, &results);
+        print_nondeterministic(&report, &results);
+        print_total(&report, start.elapsed(), &results, self.repeat);
+        self.write_junit(&results, &regressions, &failed)?;
+
+        Ok((results, averages))
+    }
+
+    /// Run a suite of board positions through the engine, and return a Vec
+    /// of SearchResult.
+    ///
+    /// Also responsible for reporting/printing the results as they come in.
+    fn run_suite(
+        &self, 
+        engine: &Path, 
+        suite: &[Epd], 
+        done: Vec<SearchResult>,
+        meta: &mut Metadata
+    ) -> anyhow::Result<Vec<SearchResult>> {
+        let start = Instant::now();
+        let mut results = done;
+        let mut failed = Vec::new();
+        let depths = self.depths(meta);
+        let searchmoves = suite.iter().map(Epd::search_moves).collect::<Vec<_>>();
+        let searches = suite_searches(suite, &searchmoves, &depths);
+        let mut engines = self.start_engines(engine, meta, searches.len())?;
+        meta.engine = Some(engines[0].id().clone());
+
+        let mut fields = Fields::from(self);
+        fields.label = suite.iter().any(|epd| epd.id().is_some())
+            || results.iter().any(|result| result.id.is_some());
+        fields.solved = suite.iter().any(|epd| !epd.best_moves().is_empty())
+            || results.iter().any(|result| result.solved().is_some());
+        fields.depth = depths.len() > 1;
+
+        let report = self.suite_report(&fields)?;
+        print_engine(&report, &engines[0]);
+        report.print_header();
+
+        // Results from an earlier, interrupted run come first
+        if !self.defer_rows() && !self.quiet {
+            for result in &results {
+                print_result(&report, result, &fields, false);
+            }
+        }
+
+        let progress = self.progress_bar(searches.len());
+
+        self.search_all(&mut engines, meta, &searches, |idx, result| {
+            // Every position is searched at each of the depths in turn
+            let epd = &suite[idx / depths.len()];
+            progress.inc(1);
+
+            let Some(mut result) = result else {
+                if !self.quiet {
+                    progress.suspend(|| report.print_row(&failed_row(&report, &epd.fen)));
+                }
+
+                failed.push(epd.fen.clone());
+                return Ok(());
+            };
+
+            result.id = epd.id();
+            result.bm = epd.best_moves();
+
+            // Print the result as a row of the report, unless the rows need 
+            // to be sorted first
+            if !self.defer_rows() && !self.quiet {
+                progress.suspend(|| print_result(&report, &result, &fields, false));
+            }
+
+            results.push(result);
+
+            if self.resume {
+                self.checkpoint(meta, &results)?;
+            }
+
+            Ok(())
+        })?;
+
+        progress.finish_and_clear();
+
+        let order = self.sort_order(results.len(), |i, key| results[i].sort_value(key));
+        let outliers = self.outliers(&results);
+
+        if self.defer_rows() && !self.quiet {
+            for &i in &order {
+                print_result(&report, &results[i], &fields, outliers[i]);
+            }
+        }
+
+        if self.format == Format::Json {
+            let sorted = order.iter().map(|&i| &results[i]).collect::<Vec<_>>();
+            println!("{}", serde_json::to_string_pretty(&sorted)?);
+        }
+
+        // Print averages, potentially behind a flag. The header already ends 
+        // in a separator when there are no rows.
+        if !self.quiet {
+            report.print_separator();
+        }
+
+        let summary = SearchResult::summarize(&without_outliers(&results, &outliers), self.aggregate);
+        let averages = summary.extract(&fields);
+
+        report.print_row(&averages);
+
+        // Print footer line
+        report.print_footer();
+        print_failed(&report, &failed);
+
+        if let Some(rule) = self.drop_outliers {
+            print_outliers(&report, &outliers, rule);
+        }
+
+        if self.format == Format::Github {
+            let summary = format!(
+                "Searched {} position(s), {}, {}, {} on average",
+                results.len(),
+                summary.nodes,
+                summary.time,
+                summary.nps,
+            );
+
+            println!("{}", report::workflow_command("notice", "chess-bench", &summary));
+        }
+        print_solved(&report, &results);
+        print_nondeterministic(&report, &results);
+        print_total(&report, start.elapsed(), &results, self.repeat);
+        self.write_junit(&results, &vec![None; results.len()], &failed)?;
+
+        Ok(results)
+    }
+
+    /// A report in the requested format, written to the `--report` file if
+    /// one was passed
+    fn new_report(&self) -> anyhow::Result<Report> {
+        let report = Report::new(self.format);
+
+        match &self.report {
+            Some(path) => report.write_to(path, self.color == ColorChoice::Always),
+            None => Ok(report),
+        }
+    }
+
+    /// The report for the results of a suite, with a column for every field
+    fn suite_report(&self, fields: &Fields) -> anyhow::Result<Report> {
+        let mut report = self.new_report()?;
+
+        report.add_col("FEN", 72).truncate();
+
+        if fields.label {
+            report.add_col("Label", 15).align(Alignment::Left).truncate();
+        }
+
+        if fields.depth {
+            report.add_col("Depth", 5);
+        }
+
+        if fields.nodes {
+            report.add_col("Nodes", 20);
+        }
+
+        // Leave room for the standard deviation when repeating searches
+        let width = if self.repeat > 1 { 16 } else { 10 };
+
+        if fields.time {
+            report.add_col("Time", width);
+        }
+
+        if fields.nps {
+            report.add_col("Nps", width);
+        }
+
+        if fields.branching {
+            report.add_col("Branching", 10);
+        }
+
+        if fields.ebf {
+            report.add_col("EBF", 10);
+        }
+
+        if fields.reached_depth {
+            report.add_col("Reached", 8);
+        }
+
+        if fields.seldepth {
+            report.add_col("Seldepth", 10);
+        }
+
+        if fields.hashfull {
+            report.add_col("Hashfull", 10);
+        }
+
+        if fields.tbhits {
+            report.add_col("TB hits", 12);
+        }
+
+        if let Some(depth) = self.target_depth.filter(|_| fields.time_to_depth) {
+            report.add_col(&format!("To depth {depth}"), 12);
+        }
+
+        if fields.score {
+            report.add_col("Score", 10);
+        }
+
+        if fields.best_move {
+            report.add_col("Best Move", 10);
+        }
+
+        if fields.solved {
+            report.add_col("Solved", 8).align(Alignment::Center);
+        }
+
+        if fields.pv {
+            report.add_col("PV", 30);
+        }
+
+        Ok(report)
+    }
+
+    /// Print the results stored in the snapshot, the way they were printed
+    /// when the snapshot was taken, without starting the engine
+    fn run_show(&self) -> anyhow::Result<()> {
+        let snapshot = Snapshot::load(&self.snapshot)?;
+
+        let results = snapshot.results.into_iter()
+            .filter(|result| self.matches_filter(result.id.as_deref()))
+            .collect::<Vec<_>>();
+
+        let results = self.window_results(results);
+
+        let mut fields = Fields::from(self);
+        fields.label = results.iter().any(|result| result.id.is_some());
+        fields.solved = results.iter().any(|result| result.solved().is_some());
+        fields.depth = results.iter().any(|result| result.depth != results[0].depth);
+
+        let report = self.suite_report(&fields)?;
+        let order = self.sort_order(results.len(), |i, key| results[i].sort_value(key));
+        let outliers = self.outliers(&results);
+
+        if let Some(engine) = &snapshot.meta.engine {
+            report.print_note(&format!("Engine: {engine}"));
+        }
+
+        report.print_header();
+
+        if !self.quiet {
+            for &i in &order {
+                print_result(&report, &results[i], &fields, outliers[i]);
+            }
+
+            report.print_separator();
+        }
+
+        if self.format == Format::Json {
+            let sorted = order.iter().map(|&i| &results[i]).collect::<Vec<_>>();
+            println!("{}", serde_json::to_string_pretty(&sorted)?);
+        }
+
+        if self.format == Format::Csv {
+            let sorted = order.iter().map(|&i| results[i].clone()).collect::<Vec<_>>();
+            print!("{}", report::to_csv(&sorted, &fields));
+        }
+
+        let averages = SearchResult::summarize(&without_outliers(&results, &outliers), self.aggregate);
+        report.print_row(&averages.extract(&fields));
+        report.print_footer();
+
+        if let Some(rule) = self.drop_outliers {
+            print_outliers(&report, &outliers, rule);
+        }
+
+        print_solved(&report, &results);
+
+        Ok(())
+    }
+
+    /// Print the average metrics of every run recorded in the `--db` 
+    /// database, oldest first, either over all of the positions or for a 
+    /// single one
+    #[cfg(feature = "history")]
+    fn run_trend(&self, position: Option<&str>) -> anyhow::Result<()> {
+        let Some(path) = &self.db else {
+            return Err(anyhow!("Pass the database to read with --db"));
+        };
+
+        // Opening the database would create it
+        if !path.exists() {
+            return Err(anyhow!("Database {} doesn't exist", path.display()));
+        }
+
+        let runs = History::open(path)?.trend(position)?;
+
+        if runs.is_empty() {
+            return Err(match position {
+                Some(position) => anyhow!("None of the runs in {} searched {position}", path.display()),
+                None => anyhow!("There are no runs recorded in {}", path.display()),
+            });
+        }
+
+        let report = self.new_report()?;
+        let mut table = Tabulator::auto();
+
+        table.add_col("Run", 0);
+        table.add_col("Date", 0);
+        table.add_col("Commit", 0).align(Alignment::Left);
+        table.add_col("Engine", 0).align(Alignment::Left);
+        table.add_col("Searches", 0);
+        table.add_col("Nodes", 0);
+        table.add_col("Time", 0);
+        table.add_col("Nps", 0);
+        table.add_col("Change", 0);
+
+        for (idx, run) in runs.iter().enumerate() {
+            // The change in nps since the run before, colored like any other
+            // change in nps
+            let change = match idx.checked_sub(1).map(|prev| runs[prev].nps) {
+                Some(prev) => diff::format_relative(NpsDiff::new(prev, run.nps).relative)
+                    .color(diff::change_color(SortKey::Nps, prev, run.nps))
+                    .to_string(),
+                None => "-".to_string(),
+            };
+
+            table.push_row(&[
+                run.id.to_string(),
+                run.timestamp.map(history::format_timestamp).unwrap_or_else(|| "-".to_string()),
+                run.commit.clone().unwrap_or_else(|| "-".to_string()),
+                run.engine.clone().unwrap_or_else(|| "-".to_string()),
+                run.positions.to_string(),
+                format!("{:#}", run.nodes),
+                format!("{:#}", run.time),
+                run.nps.to_string(),
+                change,
+            ]);
+        }
+
+        report.print_rendered(&table.render());
+
+        // The average only tells a story when every run searched the same
+        if position.is_none() && runs.iter().any(|run| run.positions != runs[0].positions) {
+            report.print_note(&format!(
+                "{} the runs searched different numbers of positions, so their averages may not be comparable",
+                "Warning:".yellow()
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Run perft on every position in the suite, and print the node counts
+    /// along with the time it took the engine to count them.
+    fn run_perft(&self, engine: &Path, depth: usize, command: &str) -> anyhow::Result<()> {
+        let start = Instant::now();
+        let suite = self.load_suite()?;
+        let command = command.replace("{depth}", &depth.to_string());
+        let mut engine = self.start_engine(engine, &self.metadata())?;
+        let mut results = Vec::new();
+
+        let mut fields = Fields::from(self);
+        fields.label = suite.iter().any(|epd| epd.id().is_some());
+        fields.nodes = true;
+        fields.time = true;
+        fields.nps = true;
+        fields.branching = false;
+        fields.ebf = false;
+        fields.reached_depth = false;
+        fields.seldepth = false;
+        fields.hashfull = false;
+        fields.tbhits = false;
+        fields.time_to_depth = false;
+        fields.score = false;
+        fields.best_move = false;
+        fields.pv = false;
+
+        let mut report = self.new_report()?;
+
+        report.add_col("FEN", 72).truncate();
+
+        if fields.label {
+            report.add_col("Label", 15).align(Alignment::Left).truncate();
+        }
+
+        report.add_col("Nodes", 20);
+        report.add_col("Time", 10);
+        report.add_col("Nps", 10);
+
+        report.print_header();
+
+        let progress = self.progress_bar(suite.len());
+
+        for epd in &suite {
+            if interrupt::interrupted() {
+                break;
+            }
+
+            let board = epd.fen.parse()?;
+            let search_start = Instant::now();
+            let nodes = engine.perft(board, &command, self.timeout())?;
+
+            // Perft doesn't report any info, so time it ourselves
+            let info = Info {
+                nodes: Some(nodes),
+                time: Some(search_start.elapsed().as_millis() as u64),
+                ..Info::default()
+            };
+
+            let mut result = SearchResult::new(board, depth, &info, String::new());
+            result.id = epd.id();
+
+            progress.inc(1);
+
+            if !self.quiet {
+                progress.suspend(|| print_result(&report, &result, &fields, false));
+            }
+
+            results.push(result);
+        }
+
+        progress.finish_and_clear();
+
+        if self.format == Format::Json {
+            println!("{}", serde_json::to_string_pretty(&results)?);
+        }
+
+        if self.format == Format::Csv {
+            print!("{}", report::to_csv(&results, &fields));
+        }
+
+        if !self.quiet {
+            report.print_separator();
+        }
+
+        let averages = SearchResult::summarize(&results, self.aggregate);
+        report.print_row(&averages.extract(&fields));
+        report.print_footer();
+        print_total(&report, start.elapsed(), &results, 1);
+
+        Ok(())
+    }
+
+    /// Run a suite of board positions through the engine without printing a
+    /// table, and return a Vec of SearchResult. Positions the engine crashed
+    /// or timed out on are left out, with a warning.
+    fn search_suite(
+        &self, 
+        engine: &Path, 
+        suite: &[Epd], 
+        meta: &mut Metadata
+    ) -> anyhow::Result<Vec<SearchResult>> {
+        let mut results = Vec::new();
+        let depths = self.depths(meta);
+        let searchmoves = suite.iter().map(Epd::search_moves).collect::<Vec<_>>();
+        let searches = suite_searches(suite, &searchmoves, &depths);
+        let mut engines = self.start_engines(engine, meta, searches.len())?;
+        meta.engine = Some(engines[0].id().clone());
+        let progress = self.progress_bar(searches.len());
+
+        self.search_all(&mut engines, meta, &searches, |idx, result| {
+            let epd = &suite[idx / depths.len()];
+            progress.inc(1);
+
+            let Some(mut result) = result else {
+                progress.suspend(|| eprintln!(
+                    "{} {} failed on {}, leaving it out", 
+                    "Warning:".yellow(),
+                    engine.display(),
+                    epd.fen
+                ));
+
+                return Ok(());
+            };
+
+            result.id = epd.id();
+            result.bm = epd.best_moves();
+            results.push(result);
+
+            Ok(())
+        })?;
+
+        progress.finish_and_clear();
+
+        Ok(results)
+    }
+
+    /// Run the suite once for every combination of the values of the swept
+    /// UCI options, and print a matrix of scores, with one row per position
+    /// and one column per combination. Below that, a table ranks the 
+    /// combinations on the summary of the suite, by the gate metric, followed
+    /// by the combinations with the highest nps and the fewest nodes.
+    ///
+    /// Every combination gets freshly spawned engines, so runs don't 
+    /// influence each other.
+    fn run_sweep(&self, engine: &Path, suite: &[Epd], sweeps: &[OptionSweep]) -> anyhow::Result<()> {
+        let mut names = HashSet::new();
+
+        if let Some(sweep) = sweeps.iter().find(|sweep| !names.insert(&sweep.name)) {
+            return Err(anyhow!("Option {} is swept more than once", sweep.name));
+        }
+
+        let count = sweep::combination_count(sweeps);
+
+        if count > sweep::MAX_COMBINATIONS {
+            eprintln!(
+                "{} the sweep has {count} combinations of options, only running the first {}",
+                "Warning:".yellow(),
+                sweep::MAX_COMBINATIONS,
+            );
+        }
+
+        let combinations = sweep::combinations(sweeps, sweep::MAX_COMBINATIONS);
+        let mut runs = Vec::new();
+
+        for combination in &combinations {
+            // Options are set in order, so the swept values override any 
+            // other setting of the same options
+            let mut meta = self.metadata();
+            meta.options.extend(combination.iter().cloned());
+
+            runs.push(self.search_suite(engine, suite, &mut meta)?);
+        }
+
+        let labels = combinations.iter()
+            .map(|combination| combination.iter()
+                .map(EngineOption::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+            )
+            .collect::<Vec<_>>();
+
+        // Positions the engine crashed on are left out of the results, so 
+        // look them up by position instead of by index
+        let lookup = runs.iter()
+            .map(|results| results.iter()
+                .map(|result| ((result.position.as_str(), result.depth), result))
+                .collect::<HashMap<_, _>>()
+            )
+            .collect::<Vec<_>>();
+
+        let depths = self.depths(&self.metadata());
+
+        // All of the scores are in before anything gets printed, so the
+        // columns can be sized to fit
+        let report = self.new_report()?;
+        let mut table = Tabulator::auto();
+
+        table.add_col("FEN", 0);
+
+        if depths.len() > 1 {
+            table.add_col("Depth", 0);
+        }
+
+        for label in &labels {
+            table.add_col(label, 0);
+        }
+
+        for epd in suite {
+            // Results hold the FEN as the engine was given it
+            let fen = epd.fen.parse::<Board>()?.to_fen();
+
+            for &depth in &depths {
+                let mut row = vec![format!("{}", epd.fen.blue())];
+
+                if depths.len() > 1 {
+                    row.push(depth.to_string());
+                }
+
+                row.extend(lookup.iter().map(|lookup| match lookup.get(&(fen.as_str(), depth)) {
+                    Some(result) => result.score.to_string(),
+                    None => "-".to_string(),
+                }));
+
+                table.push_row(&row);
+            }
+        }
+
+        let summaries = runs.iter()
+            .map(|results| SearchResult::summarize(results, self.aggregate))
+            .collect::<Vec<_>>();
+
+        // Add averages
+        table.push_separator();
+        let mut averages = vec![String::new()];
+
+        if depths.len() > 1 {
+            averages.push(String::new());
+        }
+
+        averages.extend(summaries.iter().map(|summary| summary.score.to_string()));
+        table.push_row(&averages);
+
+        report.print_rendered(&table.render());
+
+        // Rank the combinations by the gate metric, best first. Ties keep the
+        // order of the sweep.
+        let key = SortKey::from(self.gate_metric);
+        let mut ranking = (0..summaries.len()).collect::<Vec<_>>();
+
+        ranking.sort_by(|&a, &b| {
+            let ordering = summaries[a].sort_value(key).total_cmp(&summaries[b].sort_value(key));
+
+            match diff::worse_is(key) {
+                Worse::Higher => ordering,
+                Worse::Lower => ordering.reverse(),
+            }
+        });
+
+        let fields = Fields::from(self);
+        let mut table = Tabulator::auto();
+
+        table.add_col("Rank", 0);
+
+        for sweep in sweeps {
+            table.add_col(&sweep.name, 0);
+        }
+
+        table.add_col("Nodes", 0);
+        table.add_col("Time", 0);
+        table.add_col("Nps", 0);
+        table.add_col("Score", 0);
+
+        for (rank, &i) in ranking.iter().enumerate() {
+            let summary = &summaries[i];
+            let mut row = vec![(rank + 1).to_string()];
+
+            row.extend(combinations[i].iter().map(|option| option.value.clone().unwrap_or_default()));
+            row.push(fields.format(&summary.nodes));
+            row.push(fields.format(&summary.time));
+            row.push(summary.nps.to_string());
+            row.push(summary.score.to_string());
+
+            table.push_row(&row);
+        }
+
+        report.print_rendered(&table.render());
+
+        // Ties go to the first combination
+        let combinations = labels.iter().zip(&summaries);
+
+        let fastest = combinations.clone()
+            .rev()
+            .filter(|(_, summary)| summary.nps.0.is_some())
+            .max_by_key(|(_, summary)| summary.nps);
+
+        if let Some((label, summary)) = fastest {
+            report.print_note(&format!("Highest nps: {label} ({})", summary.nps));
+        }
+
+        if let Some((label, summary)) = combinations.min_by_key(|(_, summary)| summary.nodes) {
+            report.print_note(&format!("Fewest nodes: {label} ({})", fields.format(&summary.nodes)));
+        }
+
+        Ok(())
+    }
+
+    /// Run the suite with every engine, and print the results side by side,
+    /// with one row per position, and a column per engine for every metric.
+    fn run_matrix(&self, runs: &[(&Path, Option<String>)], suite: &[Epd]) -> anyhow::Result<()> {
+        let mut results = Vec::new();
+
+        for (engine, eval_file) in runs {
+            let mut meta = self.metadata();
+            meta.eval_file = eval_file.clone();
+            results.push(self.search_suite(engine, suite, &mut meta)?);
+        }
+
+        // Positions an engine crashed on are left out of its results, so look
+        // them up by position instead of by index
+        let lookup = results.iter()
+            .map(|results| results.iter()
+                .map(|result| ((result.position.as_str(), result.depth), result))
+                .collect::<HashMap<_, _>>()
+            )
+            .collect::<Vec<_>>();
+
+        // Only the metrics get a column per engine
+        let mut fields = Fields::from(self);
+        fields.pv = false;
+
+        let metrics = [
+            (fields.nodes, "Nodes"),
+            (fields.time, "Time"),
+            (fields.nps, "Nps"),
+            (fields.branching, "Branching"),
+            (fields.ebf, "EBF"),
+            (fields.reached_depth, "Reached"),
+            (fields.seldepth, "Seldepth"),
+            (fields.hashfull, "Hashfull"),
+            (fields.tbhits, "TB hits"),
+            (fields.time_to_depth, "Time to depth"),
+            (fields.score, "Score"),
+            (fields.best_move, "Best Move"),
+        ];
+
+        let metrics = metrics.iter()
+            .filter(|(shown, _)| *shown)
+            .map(|(_, name)| *name)
+            .collect::<Vec<_>>();
+
+        let file_name = |path: &Path| path.file_name()
+            .unwrap_or(path.as_os_str())
+            .to_string_lossy()
+            .to_string();
+
+        // Name the network as well when there's more than one
+        let names = runs.iter()
+            .map(|(engine, eval_file)| match eval_file {
+                Some(eval_file) if self.eval_file.len() > 1 => {
+                    format!("{}, {}", file_name(engine), file_name(Path::new(eval_file)))
+                },
+                _ => file_name(engine),
+            })
+            .collect::<Vec<_>>();
+
+        let depths = self.depths(&self.metadata());
+        let labels = suite.iter().any(|epd| epd.id().is_some());
+
+        // All of the results are in before anything gets printed, so the
+        // columns can be sized to fit
+        let report = self.new_report()?;
+        let mut table = Tabulator::auto();
+
+        table.add_col("FEN", 0);
+
+        if labels {
+            table.add_col("Label", 0).align(Alignment::Left);
+        }
+
+        if depths.len() > 1 {
+            table.add_col("Depth", 0);
+        }
+
+        for metric in &metrics {
+            for name in &names {
+                table.add_col(&format!("{metric} ({name})"), 0);
+            }
+        }
+
+        // Interleave the metrics of every engine, so the values of the same 
+        // metric end up next to each other. The FEN comes first in every row.
+        let row = |results: &[Option<&SearchResult>]| -> Vec<String> {
+            let values = results.iter()
+                .map(|result| result.map(|result| result.extract(&fields)))
+                .collect::<Vec<_>>();
+
+            (0..metrics.len())
+                .flat_map(|i| values.iter().map(move |values| match values {
+                    Some(values) => values[i + 1].clone(),
+                    None => "-".to_string(),
+                }))
+                .collect()
+        };
+
+        for epd in suite {
+            // Results hold the FEN as the engine was given it
+            let fen = epd.fen.parse::<Board>()?.to_fen();
+
+            for &depth in &depths {
+                let mut values = vec![format!("{}", epd.fen.blue())];
+
+                if labels {
+                    values.push(epd.id().unwrap_or_default());
+                }
+
+                if depths.len() > 1 {
+                    values.push(depth.to_string());
+                }
+
+                let results = lookup.iter()
+                    .map(|lookup| lookup.get(&(fen.as_str(), depth)).copied())
+                    .collect::<Vec<_>>();
+
+                values.extend(row(&results));
+                table.push_row(&values);
+            }
+        }
+
+        // Add averages
+        table.push_separator();
+
+        let summaries = results.iter()
+            .map(|results| SearchResult::summarize(results, self.aggregate))
+            .collect::<Vec<_>>();
+
+        let mut averages = vec![String::new()];
+
+        if labels {
+            averages.push(String::new());
+        }
+
+        if depths.len() > 1 {
+            averages.push(String::new());
+        }
+
+        averages.extend(row(&summaries.iter().map(Some).collect::<Vec<_>>()));
+        table.push_row(&averages);
+
+        report.print_rendered(&table.render());
+
+        Ok(())
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+///
+/// Tests
+///
+////////////////////////////////////////////////////////////////////////////////
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parse the command line, with a made-up engine
+    fn cli(args: &[&str]) -> Cli {
+        let args = ["chess-bench", "./engine"].iter().chain(args);
+        Cli::try_parse_from(args).unwrap()
+    }
+
+    /// A suite of the first few default positions
+    fn default_suite(len: usize) -> Vec<Epd> {
+        POSITIONS[..len].iter()
+            .map(|fen| fen.parse().unwrap())
+            .collect()
+    }
+
+    /// The result of searching a position in the suite, with the FEN as the
+    /// engine was given it
+    fn result(epd: &Epd, depth: usize) -> SearchResult {
+        SearchResult {
+            position: epd.fen.parse::<Board>().unwrap().to_fen(),
+            depth,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn resume_skips_the_checkpointed_searches() {
+        let output = std::env::temp_dir()
+            .join(format!("chess-bench-resume-{}.json", std::process::id()));
+        let output = output.to_str().unwrap();
+
+        let cli = cli(&["--depth", "6,8", "--output", output]);
+        let mut suite = default_suite(3);
+
+        // Interrupted halfway through the second position
+        let partial = vec![
+            result(&suite[0], 6),
+            result(&suite[0], 8),
+            result(&suite[1], 6),
+        ];
+
+        cli.checkpoint(&Metadata::default(), &partial).unwrap();
+
+        let snapshot = Snapshot::load(&cli.output).unwrap();
+        std::fs::remove_file(&cli.output).unwrap();
+
+        let meta = cli.inherit_metadata(&snapshot.meta);
+        let done = cli.skip_done(&mut suite, snapshot.results, &meta).unwrap();
+
+        // Only the first position was searched at every depth
+        let fresh = default_suite(3);
+        assert_eq!(done.len(), 2);
+        assert!(done.iter().all(|result| result.position == partial[0].position));
+        assert_eq!(suite.iter().map(|epd| &epd.fen).collect::<Vec<_>>(), [&fresh[1].fen, &fresh[2].fen]);
+
+        // Searching what's left alongside what's done covers every search once
+        let mut searches = done.iter()
+            .map(|result| (result.position.clone(), result.depth))
+            .chain(suite.iter().flat_map(|epd| [result(epd, 6), result(epd, 8)])
+                .map(|result| (result.position, result.depth)))
+            .collect::<Vec<_>>();
+
+        let total = searches.len();
+        searches.sort();
+        searches.dedup();
+
+        assert_eq!(total, 6);
+        assert_eq!(searches.len(), total);
+    }
+
+    #[test]
+    fn match_suite_follows_the_suite_order() {
+        let cli = cli(&[]);
+        let positions = default_suite(4);
+        let snapshot = positions[..3].iter().map(|epd| result(epd, 8)).collect::<Vec<_>>();
+
+        // Reordered, with the second position dropped and a new one added
+        let suite = [positions[2].clone(), positions[0].clone(), positions[3].clone()];
+        let (matched, new, gone) = cli.match_suite(snapshot.clone(), &suite).unwrap();
+
+        let matched = matched.iter().map(|result| &result.position).collect::<Vec<_>>();
+        assert_eq!(matched, [&snapshot[2].position, &snapshot[0].position]);
+
+        assert_eq!(new.len(), 1);
+        assert_eq!(new[0].fen, positions[3].fen);
+
+        assert_eq!(gone.len(), 1);
+        assert_eq!(gone[0].position, snapshot[1].position);
+    }
+
+    #[test]
+    fn suite_skips_comments_and_blank_lines() {
+        assert!(is_comment(""));
+        assert!(is_comment("# a comment"));
+        assert!(is_comment("// a comment"));
+        assert!(!is_comment("8/8/8/4k3/8/8/8/4K3 w - - 0 1"));
+
+        let cli = cli(&["--fens", concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/suite.txt")]);
+        let suite = cli.read_suite().unwrap();
+
+        let fens = suite.iter().map(|epd| epd.fen.as_str()).collect::<Vec<_>>();
+        assert_eq!(fens, [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        ]);
+
+        let labels = suite.iter().map(Epd::id).collect::<Vec<_>>();
+        assert_eq!(labels, [None, Some(String::from("kiwipete")), None]);
+    }
+
+    #[test]
+    fn timed_out_position_doesnt_abort_the_run() {
+        let cli = cli(&["--timeout", "200"]);
+        let engine = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/engines/slow_position.sh");
+
+        // The engine only stalls in the second position
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        ];
+
+        let searches = fens.iter().map(|&fen| (fen, 1, &[][..])).collect::<Vec<_>>();
+
+        // An engine that sends its best move once it's stopped, and one that
+        // has to be killed
+        for args in [vec![], vec![String::from("--ignore-stop")]] {
+            let meta = Metadata { engine_args: args, ..Default::default() };
+            let mut engines = vec![cli.start_engine(&engine, &meta).unwrap()];
+            let mut best_moves = Vec::new();
+
+            cli.search_all(&mut engines, &meta, &searches, |_, result| {
+                best_moves.push(result.map(|result| result.best_move));
+                Ok(())
+            }).unwrap();
+
+            // The best move sent after the stop isn't mistaken for the next one
+            let expected = [Some("e2e4"), None, Some("e2e4")].map(|mv| mv.map(String::from));
+            assert_eq!(best_moves, expected, "{:?}", meta.engine_args);
+        }
     }
 }
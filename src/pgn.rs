@@ -0,0 +1,226 @@
+use std::collections::HashMap;
+use std::iter::Peekable;
+use std::str::Chars;
+
+use anyhow::anyhow;
+use simbelmyne_chess::board::Board;
+
+use crate::epd::{parse_fen, san_to_move, to_xfen, Epd};
+
+////////////////////////////////////////////////////////////////////////////////
+///
+/// Game
+///
+////////////////////////////////////////////////////////////////////////////////
+/// A single game from a PGN file: its tag pairs, and the moves of the main
+/// line in SAN. Comments, variations and annotations are dropped.
+#[derive(Debug, Clone, Default)]
+pub struct Game {
+    /// The tag pairs, e.g., `White` or `FEN`, keyed by name
+    pub tags: HashMap<String, String>,
+
+    /// The moves of the main line, in SAN (e.g., `Nbxd7+`)
+    pub moves: Vec<String>,
+}
+
+impl Game {
+    /// Whether anything has been read for this game yet
+    fn is_empty(&self) -> bool {
+        self.tags.is_empty() && self.moves.is_empty()
+    }
+
+    /// A label for the game: the players, when the tags name both of them,
+    /// and the number of the game in the file otherwise
+    pub fn name(&self, number: usize) -> String {
+        let player = |tag| self.tags.get(tag)
+            .filter(|name| !name.is_empty() && name.as_str() != "?");
+
+        match (player("White"), player("Black")) {
+            (Some(white), Some(black)) => format!("{white} - {black}"),
+            _ => format!("game {number}"),
+        }
+    }
+
+    /// The position the game starts from: the `FEN` tag if there is one, and
+    /// the regular starting position otherwise
+    fn start(&self, chess960: bool) -> anyhow::Result<Board> {
+        let Some(fen) = self.tags.get("FEN") else {
+            return Ok(Board::default());
+        };
+
+        let fen = if chess960 { to_xfen(fen)? } else { fen.clone() };
+
+        parse_fen(&fen).map_err(|err| anyhow!("invalid FEN tag '{fen}' ({err})"))
+    }
+
+    /// Replay the game, and take the position after every `every` plies.
+    /// Positions where the game is over (and there's nothing left to search)
+    /// are left out. Fails on the first move that isn't legal.
+    pub fn sample(&self, number: usize, every: usize, chess960: bool) -> anyhow::Result<Vec<Epd>> {
+        let mut board = self.start(chess960)?;
+        let mut positions = Vec::new();
+
+        for (idx, san) in self.moves.iter().enumerate() {
+            let ply = idx + 1;
+
+            let mv = san_to_move(&board, san)
+                .ok_or_else(|| anyhow!("illegal move '{san}' at ply {ply}"))?;
+
+            board = board.play_move(mv);
+
+            if ply % every == 0 && !board.legal_moves::<true>().is_empty() {
+                let mut epd = Epd::from_fen(&board.to_fen());
+                let label = format!("{}, ply {ply}", self.name(number));
+                epd.operations.insert(String::from("id"), label);
+
+                positions.push(epd);
+            }
+        }
+
+        Ok(positions)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+///
+/// Parsing
+///
+////////////////////////////////////////////////////////////////////////////////
+/// Split the contents of a PGN file into games. A game ends at its result
+/// (`1-0`, `0-1`, `1/2-1/2` or `*`), or where the tags of the next game begin,
+/// so a missing result doesn't run two games together.
+pub fn parse_games(contents: &str) -> Vec<Game> {
+    let mut games = Vec::new();
+    let mut game = Game::default();
+    let mut chars = contents.chars().peekable();
+    let mut line_start = true;
+
+    while let Some(c) = chars.next() {
+        match c {
+            // Comments
+            '{' => skip_until(&mut chars, '}'),
+            ';' => skip_until(&mut chars, '\n'),
+
+            // Escaped lines
+            '%' if line_start => skip_until(&mut chars, '\n'),
+
+            // Variations
+            '(' => skip_variation(&mut chars),
+
+            '[' => {
+                if !game.moves.is_empty() {
+                    games.push(std::mem::take(&mut game));
+                }
+
+                if let Some((name, value)) = parse_tag(&mut chars) {
+                    game.tags.insert(name, value);
+                }
+            },
+
+            _ if c.is_whitespace() => {},
+
+            _ => {
+                let mut token = String::from(c);
+
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || "{}()[];".contains(c) {
+                        break;
+                    }
+
+                    token.push(c);
+                    chars.next();
+                }
+
+                match token.as_str() {
+                    "1-0" | "0-1" | "1/2-1/2" | "*" => {
+                        games.push(std::mem::take(&mut game));
+                    },
+
+                    // Numeric annotation glyphs, as in `$1`
+                    _ if token.starts_with('$') => {},
+
+                    _ => {
+                        // Move numbers, as in `12.` or `12...`, sometimes
+                        // without a space before the move
+                        let san = match token.rsplit_once('.') {
+                            Some((number, san)) if number.chars().all(|c| c.is_ascii_digit() || c == '.') => san,
+                            _ => &token,
+                        };
+
+                        // Annotations written apart from the move, as in `e4 !?`
+                        if !san.is_empty() && !san.chars().all(|c| c == '!' || c == '?') {
+                            game.moves.push(san.to_string());
+                        }
+                    },
+                }
+            },
+        }
+
+        line_start = c == '\n';
+    }
+
+    if !game.is_empty() {
+        games.push(game);
+    }
+
+    games
+}
+
+/// Skip ahead to just past the next occurrence of `end`
+fn skip_until(chars: &mut Peekable<Chars>, end: char) {
+    for c in chars.by_ref() {
+        if c == end {
+            break;
+        }
+    }
+}
+
+/// Skip a variation, along with any variations and comments nested in it.
+/// The opening parenthesis has already been read.
+fn skip_variation(chars: &mut Peekable<Chars>) {
+    let mut depth = 1;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => skip_until(chars, '}'),
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ => {},
+        }
+
+        if depth == 0 {
+            break;
+        }
+    }
+}
+
+/// Parse a tag pair, as in `[White "Carlsen, Magnus"]`. The opening bracket
+/// has already been read. Returns `None` for tags that don't have a name.
+fn parse_tag(chars: &mut Peekable<Chars>) -> Option<(String, String)> {
+    let mut contents = String::new();
+    let mut quoted = false;
+    let mut escaped = false;
+
+    for c in chars.by_ref() {
+        match c {
+            _ if escaped => {
+                contents.push(c);
+                escaped = false;
+            },
+
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            ']' if !quoted => break,
+            _ => contents.push(c),
+        }
+    }
+
+    let contents = contents.trim();
+    let (name, value) = contents.split_once(char::is_whitespace).unwrap_or((contents, ""));
+
+    if name.is_empty() {
+        return None;
+    }
+
+    Some((name.to_string(), value.trim().to_string()))
+}
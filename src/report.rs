@@ -0,0 +1,450 @@
+use std::cell::RefCell;
+use std::ffi::OsStr;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use anyhow::anyhow;
+use clap::ValueEnum;
+use colored::Colorize;
+
+use crate::fields::Fields;
+use crate::search_result::SearchResult;
+use crate::tabulator::{Alignment, Tabulator};
+
+////////////////////////////////////////////////////////////////////////////////
+///
+/// Format
+///
+////////////////////////////////////////////////////////////////////////////////
+/// The format to report the results in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum Format {
+    /// A table that's printed as the results come in
+    #[default]
+    Table,
+
+    /// Comma-separated values, with one row per position
+    Csv,
+
+    /// A GitHub-flavored Markdown table, without any colors
+    Markdown,
+
+    /// The results as JSON, or the diffs when comparing against a snapshot
+    Json,
+
+    /// GitHub Actions workflow commands, annotating the positions that 
+    /// regressed compared to the snapshot
+    Github,
+}
+
+impl Format {
+    /// Whether the output is meant to be read by other programs, rather than
+    /// by a person
+    pub fn is_machine_readable(self) -> bool {
+        matches!(self, Format::Csv | Format::Json)
+    }
+}
+
+/// When to color the output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum ColorChoice {
+    /// Only when printing to a terminal, and `NO_COLOR` isn't set
+    #[default]
+    Auto,
+
+    /// Always, even when writing the report to a file
+    Always,
+
+    /// Never
+    Never,
+}
+
+impl ColorChoice {
+    /// Whether to force the colors on or off, or `None` to leave it up to 
+    /// the terminal. `no_color` is set when the colors were turned off with
+    /// `--no-color` or `NO_COLOR`, or when stdout isn't a terminal.
+    pub fn colorize(self, no_color: bool) -> Option<bool> {
+        match self {
+            ColorChoice::Always => Some(true),
+            ColorChoice::Never => Some(false),
+            ColorChoice::Auto if no_color => Some(false),
+            ColorChoice::Auto => None,
+        }
+    }
+}
+
+/// Whether the value of `NO_COLOR` asks to turn the colors off. Any value but
+/// an empty one does.
+pub fn no_color_requested(value: Option<&OsStr>) -> bool {
+    value.is_some_and(|value| !value.is_empty())
+}
+
+////////////////////////////////////////////////////////////////////////////////
+///
+/// Report
+///
+////////////////////////////////////////////////////////////////////////////////
+/// Prints the rows of a table as they come in, in the requested format.
+///
+/// Machine-readable formats are written out in one go once all the results
+/// are in, so the printing methods don't do anything for those.
+pub struct Report {
+    format: Format,
+    table: Tabulator,
+
+    /// The file to write the report to instead of stdout, if any
+    file: Option<RefCell<BufWriter<File>>>,
+
+    /// Whether to keep the colors when writing to a file
+    keep_colors: bool,
+}
+
+impl Report {
+    pub fn new(format: Format) -> Self {
+        Self { format, table: Tabulator::new(), file: None, keep_colors: false }
+    }
+
+    /// Write the report to a file instead of stdout. Colors are stripped, 
+    /// unless `keep_colors` is set.
+    pub fn write_to(mut self, path: &Path, keep_colors: bool) -> anyhow::Result<Self> {
+        let file = File::create(path)
+            .map_err(|err| anyhow!("Failed to create report {}: {err}", path.display()))?;
+
+        self.file = Some(RefCell::new(BufWriter::new(file)));
+        self.keep_colors = keep_colors;
+        Ok(self)
+    }
+
+    pub fn add_col(&mut self, heading: &str, width: usize) -> &mut Self {
+        self.table.add_col(heading, width);
+        self
+    }
+
+    /// Set the alignment of the column that was added last
+    pub fn align(&mut self, alignment: Alignment) -> &mut Self {
+        self.table.align(alignment);
+        self
+    }
+
+    /// Cut off values that don't fit the column that was added last. Only 
+    /// applies to the table format, the other formats keep the full values.
+    pub fn truncate(&mut self) -> &mut Self {
+        self.table.truncate();
+        self
+    }
+
+    /// The number of columns in the table
+    pub fn cols(&self) -> usize {
+        self.table.cols()
+    }
+
+    pub fn print_header(&self) {
+        match self.format {
+            Format::Table => self.print_line(&self.table.header()),
+            Format::Markdown => {
+                self.print_line(&markdown_row(self.table.names()));
+
+                let alignment = self.table.alignments().iter()
+                    .map(|alignment| match alignment {
+                        Alignment::Left => ":---",
+                        Alignment::Right => "---:",
+                        Alignment::Center => ":---:",
+                    })
+                    .collect::<Vec<_>>();
+
+                self.print_line(&markdown_row(&alignment));
+            },
+            _ => {},
+        }
+    }
+
+    pub fn print_row(&self, values: &[String]) {
+        match self.format {
+            Format::Table => self.print_line(&self.table.row(values)),
+            Format::Markdown => self.print_line(&markdown_row(values)),
+            _ => {},
+        }
+    }
+
+    pub fn print_separator(&self) {
+        if self.format == Format::Table {
+            self.print_line(&self.table.row_separator());
+        }
+    }
+
+    pub fn print_footer(&self) {
+        if self.format == Format::Table {
+            self.print_line(&self.table.footer());
+        }
+    }
+
+    /// Print a message that isn't part of the table. Goes to stderr for
+    /// machine-readable formats, so it doesn't end up mixed in with the data.
+    pub fn print_note(&self, note: &str) {
+        if self.format.is_machine_readable() {
+            eprintln!("{note}");
+        } else {
+            self.print_line(note);
+        }
+    }
+
+    /// Print a table that was rendered separately, all at once
+    pub fn print_rendered(&self, table: &str) {
+        self.print_line(table);
+    }
+
+    /// Print a line to the report file, or to stdout when there is none
+    fn print_line(&self, line: &str) {
+        let Some(file) = &self.file else {
+            println!("{line}");
+            return;
+        };
+
+        let line = if self.keep_colors {
+            line.to_string()
+        } else {
+            strip_ansi_escapes::strip_str(line)
+        };
+
+        if let Err(err) = writeln!(file.borrow_mut(), "{line}") {
+            eprintln!("{} failed to write the report: {err}", "Warning:".yellow());
+        }
+    }
+}
+
+/// Render a GitHub Actions workflow command, e.g., `::warning::message`. The
+/// message is stripped of colors, and escaped so it stays on a single line.
+pub fn workflow_command(command: &str, title: &str, message: &str) -> String {
+    let message = strip_ansi_escapes::strip_str(message)
+        .replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A");
+
+    format!("::{command} title={title}::{message}")
+}
+
+/// Render the cells as a row of a Markdown table. Colors and padding are 
+/// stripped, since they don't survive being rendered as Markdown.
+fn markdown_row(cells: &[impl AsRef<str>]) -> String {
+    let cells = cells.iter()
+        .map(|cell| {
+            let cell = strip_ansi_escapes::strip_str(cell.as_ref());
+
+            cell.split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ")
+                .replace('|', "\\|")
+        })
+        .collect::<Vec<_>>();
+
+    format!("| {} |", cells.join(" | "))
+}
+
+////////////////////////////////////////////////////////////////////////////////
+///
+/// CSV
+///
+////////////////////////////////////////////////////////////////////////////////
+/// Render the results as CSV, with a header row and one row per position.
+/// Values are written without units, so they can be used in a spreadsheet.
+pub fn to_csv(results: &[SearchResult], fields: &Fields) -> String {
+    let mut header = vec!["fen"];
+
+    if fields.label {
+        header.push("label");
+    }
+
+    header.push("depth");
+
+    if fields.nodes {
+        header.push("nodes");
+    }
+
+    if fields.time {
+        header.push("time");
+    }
+
+    if fields.nps {
+        header.push("nps");
+    }
+
+    if fields.branching {
+        header.push("branching");
+    }
+
+    if fields.ebf {
+        header.push("ebf");
+    }
+
+    if fields.reached_depth {
+        header.push("reached_depth");
+    }
+
+    if fields.seldepth {
+        header.push("seldepth");
+    }
+
+    if fields.hashfull {
+        header.push("hashfull");
+    }
+
+    if fields.tbhits {
+        header.push("tbhits");
+    }
+
+    if fields.time_to_depth {
+        header.push("time_to_depth");
+    }
+
+    if fields.score {
+        header.push("score");
+    }
+
+    if fields.best_move {
+        header.push("best_move");
+    }
+
+    if fields.solved {
+        header.push("solved");
+    }
+
+    if fields.pv {
+        header.push("pv");
+    }
+
+    let mut csv = csv_row(&header);
+
+    for result in results {
+        let mut row = vec![result.position.clone()];
+
+        if fields.label {
+            row.push(result.id.clone().unwrap_or_default());
+        }
+
+        row.push(result.depth.to_string());
+
+        if fields.nodes {
+            row.push(result.nodes.0.to_string());
+        }
+
+        if fields.time {
+            row.push(result.time.0.to_string());
+        }
+
+        if fields.nps {
+            row.push(result.nps.0.map(|nps| nps.to_string()).unwrap_or_default());
+        }
+
+        if fields.branching {
+            row.push(result.branching_factor.to_string());
+        }
+
+        if fields.ebf {
+            row.push(result.ebf.to_string());
+        }
+
+        if fields.reached_depth {
+            row.push(result.reached_depth.0.to_string());
+        }
+
+        if fields.seldepth {
+            row.push(result.seldepth.0.to_string());
+        }
+
+        if fields.hashfull {
+            row.push(result.hashfull.0.to_string());
+        }
+
+        if fields.tbhits {
+            row.push(result.tbhits.0.to_string());
+        }
+
+        if fields.time_to_depth {
+            row.push(result.time_to_depth.0.map(|time| time.0.to_string()).unwrap_or_default());
+        }
+
+        if fields.score {
+            row.push(result.score.to_string());
+        }
+
+        if fields.best_move {
+            row.push(result.best_move.clone());
+        }
+
+        if fields.solved {
+            row.push(result.solved().map(|solved| solved.to_string()).unwrap_or_default());
+        }
+
+        if fields.pv {
+            row.push(result.pv.join(" "));
+        }
+
+        csv.push_str(&csv_row(&row));
+    }
+
+    csv
+}
+
+/// Join the cells into a single CSV line, quoting any cells that need it
+fn csv_row(cells: &[impl AsRef<str>]) -> String {
+    let cells = cells.iter()
+        .map(|cell| {
+            let cell = cell.as_ref();
+
+            if cell.contains([',', '"', '\n']) {
+                format!("\"{}\"", cell.replace('"', "\"\""))
+            } else {
+                cell.to_string()
+            }
+        })
+        .collect::<Vec<_>>();
+
+    format!("{}\n", cells.join(","))
+}
+
+////////////////////////////////////////////////////////////////////////////////
+///
+/// Tests
+///
+////////////////////////////////////////////////////////////////////////////////
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_color_turns_colors_off() {
+        assert!(no_color_requested(Some(OsStr::new("1"))));
+        assert!(!no_color_requested(Some(OsStr::new(""))));
+        assert!(!no_color_requested(None));
+
+        assert_eq!(ColorChoice::Never.colorize(false), Some(false));
+        assert_eq!(ColorChoice::Auto.colorize(true), Some(false));
+        assert_eq!(ColorChoice::Auto.colorize(false), None);
+        assert_eq!(ColorChoice::Always.colorize(true), Some(true));
+    }
+
+    #[test]
+    fn disabled_colors_leave_no_escape_sequences() {
+        // Other tests only ever turn the colors off, so this can't race them
+        colored::control::set_override(false);
+        assert!(!"Warning:".yellow().to_string().contains('\x1b'));
+
+        // Reports written to a file are stripped regardless
+        let path = std::env::temp_dir()
+            .join(format!("chess-bench-report-{}.txt", std::process::id()));
+
+        let mut report = Report::new(Format::Table).write_to(&path, false).unwrap();
+        report.add_col("Nodes", 10);
+        report.print_header();
+        report.print_row(&[String::from("\x1b[31m123456\x1b[0m")]);
+        report.print_footer();
+        drop(report);
+
+        let written = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(written.contains("123456"));
+        assert!(!written.contains('\x1b'), "{written:?}");
+    }
+}
@@ -6,7 +6,10 @@ use colored::Colorize;
 use serde::{Deserialize, Serialize};
 use simbelmyne_chess::board::Board;
 
-use crate::{diff::{BFactor, Nodes, Nps, Score, Time}, fields::{Extract, Fields}};
+use crate::epd::san_to_uci;
+use crate::info::Info;
+use crate::stats::{self, Aggregate};
+use crate::{diff::{BFactor, Hashfull, Nodes, Nps, ReachedDepth, Score, Seldepth, TbHits, Time, TimeToDepth}, fields::{Extract, Fields, SortKey}};
 
 ////////////////////////////////////////////////////////////////////////////////
 ///
@@ -22,12 +25,95 @@ pub struct SearchResult {
     pub nps: Nps,
     pub score: Score,
     pub branching_factor: BFactor,
+    #[serde(default)]
+    pub best_move: String,
+    #[serde(default)]
+    pub pv: Vec<String>,
+    #[serde(default)]
+    pub seldepth: Seldepth,
+
+    /// The depth the engine reported last. Differs from the requested depth
+    /// when searching by time or nodes, or when the engine stopped early.
+    #[serde(default)]
+    pub reached_depth: ReachedDepth,
+
+    /// How full the hash table was at the end of the search, in permille
+    #[serde(default)]
+    pub hashfull: Hashfull,
+
+    /// The number of tablebase hits, 0 when the engine didn't report any
+    #[serde(default)]
+    pub tbhits: TbHits,
+
+    /// The effective branching factor, from the node counts of the last two
+    /// depths the engine reported. Falls back to the branching factor when 
+    /// those weren't reported.
+    #[serde(default)]
+    pub ebf: BFactor,
+
+    /// The time at which the engine first reported the target depth, with
+    /// `--target-depth`
+    #[serde(default, skip_serializing_if = "TimeToDepth::is_none")]
+    pub time_to_depth: TimeToDepth,
+
+    /// Any additional lines when searching with MultiPV. The main line is 
+    /// stored in the fields above, so this is empty for regular searches.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub lines: Vec<PvLine>,
+
+    /// The label of the position: its EPD `id`, or the label of a 
+    /// `label;fen` line
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+
+    /// The best moves an EPD suite expects for the position, in SAN
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bm: Vec<String>,
+
+    /// The root moves the search was restricted to, in UCI notation, if any
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub searchmoves: Vec<String>,
+
+    /// Every iteration the engine reported on the way to the final result.
+    /// Only recorded with `--record-progress`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub progress: Vec<Progress>,
+
+    /// The spread of the metrics when the position was searched repeatedly
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stddev: Option<Stddev>,
+
+    /// Whether repeated searches of the position disagreed on the node count
+    /// or best move. Only checked with `--check-determinism`.
+    #[serde(skip)]
+    pub nondeterministic: bool,
 }
 
 impl SearchResult {
-    pub fn new(board: Board, nodes: u32, time: u64, score: i32, depth: usize) -> Self {
-        let nps = nodes / time as u32;
-        let branching_factor = f32::powf(nodes as f32, 1.0 / depth as f32);
+    /// Create a new SearchResult from the final info reported by the engine
+    pub fn new(board: Board, depth: usize, info: &Info, best_move: String) -> Self {
+        let nodes = info.nodes.unwrap_or_default();
+        let time = info.time.unwrap_or_default();
+        let score = info.score.unwrap_or_default();
+        let seldepth = info.seldepth.unwrap_or_default();
+        let reached_depth = info.depth.unwrap_or_default();
+        let hashfull = info.hashfull.unwrap_or_default();
+        let tbhits = info.tbhits.unwrap_or_default();
+        let pv = info.pv.clone();
+
+        // Always derive the nps from the final nodes and time, rather than
+        // trusting whatever the engine reported. Engines happily report 0ms 
+        // for trivial searches, so treat the time as at least 1ms to avoid 
+        // dividing by zero.
+        let nps = match (info.nodes, info.time) {
+            (Some(nodes), Some(time)) => Some(nodes / time.max(1)),
+            _ => None,
+        };
+        let branching_factor = if depth == 0 {
+            0.0
+        } else {
+            f32::powf(nodes as f32, 1.0 / depth as f32)
+        };
 
         Self {
             position: board.to_fen(),
@@ -36,9 +122,301 @@ impl SearchResult {
             time: Time(time),
             nps: Nps(nps),
             branching_factor: BFactor(branching_factor),
-            score: Score(score),
+            score,
+            best_move,
+            pv,
+            seldepth: Seldepth(seldepth),
+            reached_depth: ReachedDepth(reached_depth),
+            hashfull: Hashfull(hashfull),
+            tbhits: TbHits(tbhits),
+            ebf: BFactor(branching_factor),
+            time_to_depth: TimeToDepth(None),
+            lines: Vec::new(),
+            id: None,
+            bm: Vec::new(),
+            searchmoves: Vec::new(),
+            progress: Vec::new(),
+            stddev: None,
+            nondeterministic: false,
+        }
+    }
+
+    /// Combine repeated searches of the same position into a single result,
+    /// averaging the nodes, time and nps, and recording their spread. Any 
+    /// other fields are taken from the last run.
+    pub fn aggregate(runs: Vec<SearchResult>) -> Self {
+        let metric = |f: fn(&SearchResult) -> f64| runs.iter().map(f).collect::<Vec<_>>();
+
+        let nodes = metric(|run| run.nodes.0 as f64);
+        let time = metric(|run| run.time.0 as f64);
+        let branching = metric(|run| run.branching_factor.0 as f64);
+        let ebf = metric(|run| run.ebf.0 as f64);
+
+        // The nps is only available if it was available for every run
+        let nps = runs.iter()
+            .map(|run| run.nps.0.map(|nps| nps as f64))
+            .collect::<Option<Vec<_>>>();
+
+        let nps_stddev = nps.as_deref().and_then(stats::stddev);
+
+        let stddev = match (stats::stddev(&nodes), stats::stddev(&time), nps_stddev) {
+            (Some(nodes), Some(time), Some(nps)) => Some(Stddev { nodes, time, nps }),
+            _ => None,
+        };
+
+        let mut result = runs.into_iter().last().unwrap_or_default();
+        result.nodes = Nodes(stats::mean(&nodes).round() as u64);
+        result.time = Time(stats::mean(&time).round() as u64);
+        result.nps = Nps(nps.map(|nps| stats::mean(&nps).round() as u64));
+        result.branching_factor = BFactor(stats::mean(&branching) as f32);
+        result.ebf = BFactor(stats::mean(&ebf) as f32);
+        result.stddev = stddev;
+
+        result
+    }
+
+    /// Summarize the results into a single row, averaging every metric the 
+    /// requested way.
+    ///
+    /// The mean of the (effective) branching factors is a geometric mean, 
+    /// since they're ratios. The mean nps is the total number of nodes over 
+    /// the total time, so short searches don't count as much as long ones.
+    pub fn summarize(results: &[SearchResult], aggregate: Aggregate) -> Self {
+        let mut summary = results.iter().cloned().sum::<SearchResult>() / results.len().max(1);
+
+        // Only the positions where the engine reached the target depth count
+        let times_to_depth = results.iter()
+            .filter_map(|result| result.time_to_depth.0)
+            .map(|time| time.0 as f64)
+            .collect::<Vec<_>>();
+
+        if !times_to_depth.is_empty() {
+            summary.time_to_depth = TimeToDepth(Some(Time(stats::mean(&times_to_depth).round() as u64)));
+        }
+
+        if aggregate == Aggregate::Mean {
+            let branching = results.iter()
+                .map(|result| result.branching_factor.0 as f64)
+                .collect::<Vec<_>>();
+
+            summary.branching_factor = BFactor(stats::geometric_mean(&branching) as f32);
+
+            let ebf = results.iter()
+                .map(|result| result.ebf.0 as f64)
+                .collect::<Vec<_>>();
+
+            summary.ebf = BFactor(stats::geometric_mean(&ebf) as f32);
+            summary.nps = Self::total_nps(results);
+
+            // Mate scores are left out of the sum, so only the centipawn 
+            // scores count towards the average
+            let cps = results.iter()
+                .filter(|result| matches!(result.score, Score::Cp(_)))
+                .count();
+
+            summary.score = results.iter()
+                .map(|result| result.score)
+                .reduce(Add::add)
+                .unwrap_or_default()
+                .average(cps);
+        }
+
+        if aggregate == Aggregate::Median {
+            summary.nodes = stats::median_by(
+                results.iter().map(|result| result.nodes),
+                |nodes| nodes.0 as f64,
+            );
+
+            summary.time = stats::median_by(
+                results.iter().map(|result| result.time),
+                |time| time.0 as f64,
+            );
+
+            summary.nps = stats::median_by(
+                results.iter().map(|result| result.nps).filter(|nps| nps.0.is_some()),
+                |nps| nps.0.unwrap_or_default() as f64,
+            );
+
+            summary.branching_factor = stats::median_by(
+                results.iter().map(|result| result.branching_factor),
+                |bf| bf.0 as f64,
+            );
+
+            summary.ebf = stats::median_by(
+                results.iter().map(|result| result.ebf),
+                |ebf| ebf.0 as f64,
+            );
+
+            summary.seldepth = stats::median_by(
+                results.iter().map(|result| result.seldepth),
+                |seldepth| seldepth.0 as f64,
+            );
+
+            summary.reached_depth = stats::median_by(
+                results.iter().map(|result| result.reached_depth),
+                |depth| depth.0 as f64,
+            );
+
+            summary.hashfull = stats::median_by(
+                results.iter().map(|result| result.hashfull),
+                |hashfull| hashfull.0 as f64,
+            );
+
+            summary.tbhits = stats::median_by(
+                results.iter().map(|result| result.tbhits),
+                |tbhits| tbhits.0 as f64,
+            );
+
+            summary.time_to_depth = stats::median_by(
+                results.iter().map(|result| result.time_to_depth).filter(|time| time.0.is_some()),
+                |time| time.0.unwrap_or_default().0 as f64,
+            );
+
+            summary.score = stats::median_by(
+                results.iter().map(|result| result.score),
+                |score| score.key() as f64,
+            );
+        }
+
+        summary
+    }
+}
+
+impl SearchResult {
+    /// The nps over all of the results combined, as if they were a single 
+    /// search. Results without an nps are left out.
+    pub fn total_nps(results: &[SearchResult]) -> Nps {
+        let available = results.iter()
+            .filter(|result| result.nps.0.is_some())
+            .collect::<Vec<_>>();
+
+        if available.is_empty() {
+            return Nps(None);
+        }
+
+        let nodes = available.iter().map(|result| result.nodes.0).sum::<u64>();
+        let time = available.iter().map(|result| result.time.0).sum::<u64>();
+
+        Nps(Some(nodes / time.max(1)))
+    }
+
+    /// Whether the engine found one of the best moves the EPD suite expects.
+    /// Returns `None` for positions that don't have a `bm` operation.
+    pub fn solved(&self) -> Option<bool> {
+        if self.bm.is_empty() {
+            return None;
+        }
+
+        let board = self.position.parse().ok()?;
+        let best_move = self.best_move.to_lowercase();
+
+        Some(self.bm.iter().any(|san| san_to_uci(&board, san) == Some(best_move.clone())))
+    }
+
+    /// The effective branching factor: the ratio between the node counts of
+    /// the last two depths in the progress of a search. When the engine 
+    /// skipped depths in between, the ratio is spread over them. Returns 
+    /// `None` when there aren't two depths to compare.
+    pub fn effective_branching_factor(progress: &[Progress]) -> Option<BFactor> {
+        let last = progress.last()?;
+        let previous = progress.iter().rev().find(|iteration| iteration.depth < last.depth)?;
+
+        if previous.nodes.0 == 0 {
+            return None;
+        }
+
+        let ratio = last.nodes.0 as f32 / previous.nodes.0 as f32;
+        let depths = (last.depth - previous.depth) as f32;
+
+        Some(BFactor(ratio.powf(1.0 / depths)))
+    }
+
+    /// The value of the given metric, for sorting rows
+    pub fn sort_value(&self, key: SortKey) -> f32 {
+        match key {
+            SortKey::Nodes => self.nodes.0 as f32,
+            SortKey::Time => self.time.0 as f32,
+            SortKey::Nps => self.nps.0.unwrap_or_default() as f32,
+            SortKey::Branching => self.branching_factor.0,
+            SortKey::Score => self.score.key() as f32,
         }
     }
+
+    /// Extract a sub-row for every additional MultiPV line. Only the score, 
+    /// best move and PV columns are filled in.
+    pub fn extract_lines(&self, fields: &Fields) -> Vec<Vec<String>> {
+        self.lines.iter().enumerate().map(|(i, line)| {
+            let mut values = Vec::new();
+
+            values.push(format!("  multipv {}", i + 2));
+
+            if fields.label {
+                values.push(String::new())
+            }
+
+            if fields.depth {
+                values.push(String::new())
+            }
+
+            if fields.nodes {
+                values.push(String::new())
+            }
+
+            if fields.time {
+                values.push(String::new())
+            }
+
+            if fields.nps {
+                values.push(String::new())
+            }
+
+            if fields.branching {
+                values.push(String::new())
+            }
+
+            if fields.ebf {
+                values.push(String::new())
+            }
+
+            if fields.reached_depth {
+                values.push(String::new())
+            }
+
+            if fields.seldepth {
+                values.push(String::new())
+            }
+
+            if fields.hashfull {
+                values.push(String::new())
+            }
+
+            if fields.tbhits {
+                values.push(String::new())
+            }
+
+            if fields.time_to_depth {
+                values.push(String::new())
+            }
+
+            if fields.score {
+                values.push(line.score.to_string())
+            }
+
+            if fields.best_move {
+                values.push(line.pv.first().cloned().unwrap_or_default())
+            }
+
+            if fields.solved {
+                values.push(String::new())
+            }
+
+            if fields.pv {
+                values.push(line.pv.join(" "))
+            }
+
+            values
+        }).collect()
+    }
 }
 
 impl Extract for SearchResult {
@@ -47,26 +425,82 @@ impl Extract for SearchResult {
 
         values.push(format!("{}", self.position.to_string().blue()));
 
+        if fields.label {
+            values.push(self.id.clone().unwrap_or_default())
+        }
+
+        // The averages row has no position, and no single depth either
+        if fields.depth && self.position.is_empty() {
+            values.push(String::new())
+        } else if fields.depth {
+            values.push(self.depth.to_string())
+        }
+
         if fields.nodes {
-            values.push(self.nodes.to_string())
+            values.push(fields.format(&self.nodes))
         }
 
         if fields.time {
-            values.push(self.time.to_string())
+            match self.stddev {
+                Some(stddev) => values.push(format!("{} ± {:.0}", fields.format(&self.time), stddev.time)),
+                None => values.push(fields.format(&self.time)),
+            }
         }
 
         if fields.nps {
-            values.push(self.nps.to_string())
+            match self.stddev {
+                Some(stddev) => values.push(format!("{} ± {:.0}", self.nps, stddev.nps)),
+                None => values.push(self.nps.to_string()),
+            }
         }
 
         if fields.branching {
             values.push(self.branching_factor.to_string())
         }
 
+        if fields.ebf {
+            values.push(self.ebf.to_string())
+        }
+
+        if fields.reached_depth {
+            values.push(self.reached_depth.to_string())
+        }
+
+        if fields.seldepth {
+            values.push(self.seldepth.to_string())
+        }
+
+        if fields.hashfull {
+            values.push(self.hashfull.to_string())
+        }
+
+        if fields.tbhits {
+            values.push(self.tbhits.to_string())
+        }
+
+        if fields.time_to_depth {
+            values.push(fields.format(&self.time_to_depth))
+        }
+
         if fields.score {
             values.push(self.score.to_string())
         }
 
+        if fields.best_move {
+            values.push(self.best_move.clone())
+        }
+
+        if fields.solved {
+            values.push(match self.solved() {
+                Some(true) => format!("{}", "✓".green()),
+                Some(false) => format!("{}", "✗".red()),
+                None => String::new(),
+            })
+        }
+
+        if fields.pv {
+            values.push(self.pv.join(" "))
+        }
 
         values
     }
@@ -84,6 +518,27 @@ impl Add for SearchResult {
             nps: self.nps + rhs.nps,
             score: self.score + rhs.score,
             branching_factor: self.branching_factor + rhs.branching_factor,
+            best_move: String::new(),
+            pv: Vec::new(),
+            seldepth: self.seldepth + rhs.seldepth,
+            reached_depth: self.reached_depth + rhs.reached_depth,
+            hashfull: self.hashfull + rhs.hashfull,
+            tbhits: self.tbhits + rhs.tbhits,
+            ebf: self.ebf + rhs.ebf,
+            time_to_depth: self.time_to_depth + rhs.time_to_depth,
+            lines: Vec::new(),
+            id: None,
+            bm: Vec::new(),
+            searchmoves: Vec::new(),
+            progress: Vec::new(),
+
+            // The averages row shows the average spread of the positions
+            stddev: match (self.stddev, rhs.stddev) {
+                (Some(first), Some(second)) => Some(first + second),
+                (stddev, None) | (None, stddev) => stddev,
+            },
+
+            nondeterministic: false,
         }
     }
 }
@@ -100,6 +555,21 @@ impl Div<usize> for SearchResult {
             nps: self.nps / rhs,
             score: self.score / rhs,
             branching_factor: self.branching_factor / rhs,
+            best_move: self.best_move,
+            pv: self.pv,
+            seldepth: self.seldepth / rhs,
+            reached_depth: self.reached_depth / rhs,
+            hashfull: self.hashfull / rhs,
+            tbhits: self.tbhits / rhs,
+            ebf: self.ebf / rhs,
+            time_to_depth: self.time_to_depth / rhs,
+            lines: self.lines,
+            id: self.id,
+            bm: self.bm,
+            searchmoves: self.searchmoves,
+            progress: self.progress,
+            stddev: self.stddev.map(|stddev| stddev / rhs),
+            nondeterministic: self.nondeterministic,
         }
     }
 }
@@ -109,3 +579,243 @@ impl Sum for SearchResult {
         iter.fold(Self::default(), |acc, val| acc + val)
     }
 }
+
+////////////////////////////////////////////////////////////////////////////////
+///
+/// Stddev
+///
+////////////////////////////////////////////////////////////////////////////////
+/// The sample standard deviation of the metrics across repeated searches
+#[derive(Serialize, Deserialize, Default, Clone, Copy, Debug)]
+pub struct Stddev {
+    pub nodes: f64,
+    pub time: f64,
+    pub nps: f64,
+}
+
+impl Add for Stddev {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self {
+            nodes: self.nodes + rhs.nodes,
+            time: self.time + rhs.time,
+            nps: self.nps + rhs.nps,
+        }
+    }
+}
+
+impl Div<usize> for Stddev {
+    type Output = Self;
+
+    fn div(self, rhs: usize) -> Self::Output {
+        Self {
+            nodes: self.nodes / rhs as f64,
+            time: self.time / rhs as f64,
+            nps: self.nps / rhs as f64,
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+///
+/// PvLine
+///
+////////////////////////////////////////////////////////////////////////////////
+/// A secondary line reported when searching with MultiPV
+#[derive(Serialize, Deserialize, Default, Clone)]
+pub struct PvLine {
+    pub score: Score,
+    pub pv: Vec<String>,
+}
+
+impl From<Info> for PvLine {
+    fn from(info: Info) -> Self {
+        Self { 
+            score: info.score.unwrap_or_default(), 
+            pv: info.pv,
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+///
+/// Progress
+///
+////////////////////////////////////////////////////////////////////////////////
+/// The engine's report of a single iteration of the search, to follow how the
+/// search converged on its result
+#[derive(Serialize, Deserialize, Default, Clone)]
+pub struct Progress {
+    pub depth: usize,
+    pub seldepth: Seldepth,
+    pub nodes: Nodes,
+    pub time: Time,
+    pub score: Score,
+    pub pv: Vec<String>,
+}
+
+impl From<Info> for Progress {
+    fn from(info: Info) -> Self {
+        Self {
+            depth: info.depth.unwrap_or_default(),
+            seldepth: Seldepth(info.seldepth.unwrap_or_default()),
+            nodes: Nodes(info.nodes.unwrap_or_default()),
+            time: Time(info.time.unwrap_or_default()),
+            score: info.score.unwrap_or_default(),
+            pv: info.pv,
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+///
+/// Tests
+///
+////////////////////////////////////////////////////////////////////////////////
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STARTPOS: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+    /// The result the engine's final info line would give
+    fn search(depth: usize, info: &str) -> SearchResult {
+        let board = STARTPOS.parse().unwrap();
+        SearchResult::new(board, depth, &info.parse().unwrap(), String::from("e2e4"))
+    }
+
+    /// A result with the given node count and time, in ms
+    fn result(nodes: u64, time: u64) -> SearchResult {
+        SearchResult {
+            nodes: Nodes(nodes),
+            time: Time(time),
+            nps: Nps(Some(nodes / time.max(1))),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn total_nps_weighs_long_searches_more() {
+        // One long search, and one short one that's ten times as fast
+        let results = [result(1_000_000, 1000), result(100_000, 10)];
+
+        let naive = results.iter().filter_map(|result| result.nps.0).sum::<u64>() / 2;
+        let total = SearchResult::total_nps(&results);
+
+        assert_eq!(naive, 5500);
+        assert_eq!(total.0, Some(1_100_000 / 1010));
+        assert_eq!(SearchResult::summarize(&results, Aggregate::Mean).nps.0, total.0);
+    }
+
+    #[test]
+    fn instant_search_has_a_finite_nps() {
+        // Engines report 0ms for trivial searches
+        let result = search(1, "info depth 1 nodes 21 time 0");
+
+        assert_eq!(result.nps.0, Some(21));
+    }
+
+    #[test]
+    fn missing_nodes_leaves_nps_missing() {
+        let result = search(8, "info depth 8 time 250 score cp 20");
+
+        assert_eq!(result.nps.0, None);
+        assert_eq!(result.nps.to_string(), "n/a");
+    }
+
+    #[test]
+    fn depth_zero_keeps_the_average_branching_factor_finite() {
+        let results = [
+            search(0, "info depth 0 nodes 1 time 0"),
+            search(4, "info depth 4 nodes 256 time 1"),
+        ];
+
+        assert_eq!(results[0].branching_factor.0, 0.0);
+
+        for aggregate in [Aggregate::Mean, Aggregate::Median] {
+            let summary = SearchResult::summarize(&results, aggregate);
+
+            assert!(summary.branching_factor.0.is_finite());
+            assert!(summary.ebf.0.is_finite());
+        }
+
+        // The depth 0 search has no branching factor to average in
+        let summary = SearchResult::summarize(&results, Aggregate::Mean);
+        assert!((summary.branching_factor.0 - 4.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn aggregate_records_the_spread_of_the_runs() {
+        // The squared deviations from the mean of 5 add up to 32
+        let runs = [2, 4, 4, 4, 5, 5, 7, 9].map(|ms| result(ms * 1000, ms));
+        let expected = (32.0f64 / 7.0).sqrt();
+
+        let result = SearchResult::aggregate(runs.to_vec());
+        let stddev = result.stddev.unwrap();
+
+        assert_eq!(result.nodes.0, 5000);
+        assert_eq!(result.time.0, 5);
+        assert!((stddev.nodes - 1000.0 * expected).abs() < 1e-6);
+        assert!((stddev.time - expected).abs() < 1e-6);
+        assert_eq!(stddev.nps, 0.0);
+    }
+
+    #[test]
+    fn seldepth_is_read_from_the_seldepth_field() {
+        let info = "info depth 8 seldepth 14 nodes 1000 time 1";
+        let result = search(8, info);
+
+        assert_eq!(result.seldepth.0, 14);
+        assert_eq!(result.reached_depth.0, 8);
+        assert_eq!(Progress::from(info.parse::<Info>().unwrap()).seldepth.0, 14);
+
+        // An engine that doesn't report a seldepth doesn't get the depth 
+        // in its place
+        let info = "info depth 8 nodes 1000 time 1";
+        let result = search(8, info);
+
+        assert_eq!(result.seldepth.0, 0);
+        assert_eq!(Progress::from(info.parse::<Info>().unwrap()).seldepth.0, 0);
+    }
+
+    #[test]
+    fn hashfull_is_read_in_permille_and_shown_as_a_percentage() {
+        let result = search(8, "info depth 8 hashfull 425 nodes 1000 time 1");
+
+        assert_eq!(result.hashfull.0, 425);
+        assert_eq!(result.hashfull.to_string(), "42.5%");
+
+        // A suite of full hash tables adds up to more than fits in a u16
+        let results = vec![search(8, "info depth 8 hashfull 1000 nodes 1000 time 1"); 100];
+        let total = results.iter().cloned().sum::<SearchResult>();
+        assert_eq!(total.hashfull.0, 100_000);
+
+        let summary = SearchResult::summarize(&results, Aggregate::Mean);
+        assert_eq!(summary.hashfull.to_string(), "100.0%");
+    }
+
+    #[test]
+    fn suite_nodes_sum_past_u32_max() {
+        let results = vec![result(3_000_000_000, 1000); 3];
+
+        let total = results.iter().cloned().sum::<SearchResult>();
+        assert_eq!(total.nodes.0, 9_000_000_000);
+        assert_eq!(total.time.0, 3000);
+
+        let summary = SearchResult::summarize(&results, Aggregate::Mean);
+        assert_eq!(summary.nodes.0, 3_000_000_000);
+        assert_eq!(summary.nps.0, Some(3_000_000));
+    }
+
+    #[test]
+    fn average_score_leaves_out_mates() {
+        let results = [
+            search(8, "info depth 8 nodes 1000 time 1 score cp 100"),
+            search(8, "info depth 8 nodes 1000 time 1 score mate 3"),
+            search(8, "info depth 8 nodes 1000 time 1 score cp -20"),
+        ];
+
+        assert_eq!(SearchResult::summarize(&results, Aggregate::Mean).score, Score::Cp(40));
+    }
+}
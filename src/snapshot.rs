@@ -0,0 +1,308 @@
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use serde::de::Error;
+use serde_json::{json, Value};
+
+use crate::engine::EngineOption;
+use crate::search_result::SearchResult;
+
+////////////////////////////////////////////////////////////////////////////////
+///
+/// Snapshot
+///
+////////////////////////////////////////////////////////////////////////////////
+/// The version of the snapshot format written by this build. Bump it whenever
+/// older snapshots need more than defaults for the new fields to be read 
+/// correctly, and add a step to [`Snapshot::migrate`].
+pub const VERSION: u32 = 2;
+
+/// A saved benchmark run, along with the settings it was produced with
+#[derive(Serialize, Deserialize, Default)]
+pub struct Snapshot {
+    /// The version of the format the snapshot was written in. Snapshots from 
+    /// before the format was versioned are version 1.
+    #[serde(default = "legacy_version")]
+    pub version: u32,
+
+    #[serde(default)]
+    pub meta: Metadata,
+    pub results: Vec<SearchResult>,
+}
+
+/// The version of snapshots that don't have one
+fn legacy_version() -> u32 {
+    1
+}
+
+impl Snapshot {
+    pub fn new(meta: Metadata, results: Vec<SearchResult>) -> Self {
+        Self { version: VERSION, meta, results }
+    }
+
+    /// Load the snapshot from a file. When the file can't be parsed (e.g., 
+    /// because it got cut off, or was edited by hand), the error names the 
+    /// file, and where parsing failed.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let file = File::open(path)
+            .map_err(|err| anyhow!("Failed to open snapshot {}: {err}", path.display()))?;
+
+        Self::from_reader(BufReader::new(file)).map_err(|err| anyhow!(
+            "Snapshot {} is malformed: {err}\n\
+            Pass --force-suite to run the suite without it, along with --save \
+            to overwrite it with a new snapshot",
+            path.display()
+        ))
+    }
+
+    /// Read a snapshot in the current or any older version of the format, 
+    /// and bring it up to date. The JSON is parsed up front, so syntax errors 
+    /// come with a line and column, and errors in the contents name the field
+    /// that's wrong.
+    fn from_reader(reader: impl Read) -> serde_json::Result<Self> {
+        let json: Value = serde_json::from_reader(reader)?;
+
+        let version = match json.get("version") {
+            None => legacy_version(),
+            Some(version) => version.as_u64()
+                .and_then(|version| u32::try_from(version).ok())
+                .ok_or_else(|| serde_json::Error::custom(format!("invalid version {version}")))?,
+        };
+
+        if version > VERSION {
+            return Err(serde_json::Error::custom(format!(
+                "snapshot is version {version}, but this build only reads up to version {VERSION}. \
+                Upgrade chess-bench to read it"
+            )));
+        }
+
+        Self::migrate(json, version)
+    }
+
+    /// Bring a snapshot from an older version of the format up to date, one 
+    /// version at a time. Fields that were added without changing the version
+    /// are filled with their defaults when deserializing.
+    fn migrate(mut json: Value, version: u32) -> serde_json::Result<Self> {
+        // Version 1 snapshots started out as a bare list of results
+        if version < 2 && json.is_array() {
+            json = json!({ "results": json });
+        }
+
+        let mut snapshot: Self = serde_json::from_value(json)?;
+
+        // Version 1 snapshots from before the effective branching factor was
+        // stored fall back to the branching factor
+        if version < 2 {
+            for result in &mut snapshot.results {
+                if result.ebf.0 == 0.0 {
+                    result.ebf = SearchResult::effective_branching_factor(&result.progress)
+                        .unwrap_or(result.branching_factor);
+                }
+            }
+        }
+
+        snapshot.version = VERSION;
+
+        Ok(snapshot)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+///
+/// Metadata
+///
+////////////////////////////////////////////////////////////////////////////////
+/// The engine and settings a snapshot was produced with
+#[derive(Serialize, Deserialize, Default, Clone)]
+pub struct Metadata {
+    /// The engine that produced the snapshot, as it identified itself
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub engine: Option<EngineId>,
+
+    /// When the snapshot was taken, in seconds since the Unix epoch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<u64>,
+
+    /// The depths every position was searched to, when searching to a fixed
+    /// depth
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depth: Vec<usize>,
+
+    /// The size of the transposition table in MB, if one was configured
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<usize>,
+
+    /// The number of search threads, if configured
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub threads: Option<u32>,
+
+    /// The time spent searching each position in ms, when searching by time
+    /// rather than depth
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub movetime: Option<u64>,
+
+    /// The node limit for each search, when searching by nodes rather than
+    /// depth
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_nodes: Option<usize>,
+
+    /// The directories the engine loaded Syzygy tablebases from, as passed
+    /// to its `SyzygyPath` option
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub syzygy: Option<String>,
+
+    /// The NNUE network the engine evaluated with, as passed to its 
+    /// `EvalFile` option
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eval_file: Option<String>,
+
+    /// Whether the engine was switched to Chess960 with `UCI_Chess960`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub chess960: bool,
+
+    /// Whether the hash table was kept between positions, rather than 
+    /// cleared before every one of them
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub shared_hash: bool,
+
+    /// The depth the searches were timed to, with `--target-depth`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_depth: Option<usize>,
+
+    /// The seed the positions were shuffled with, if they were shuffled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+
+    /// Any other UCI options that were set, in the order they were applied
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub options: Vec<EngineOption>,
+
+    /// The arguments the engine binary was started with
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub engine_args: Vec<String>,
+}
+
+////////////////////////////////////////////////////////////////////////////////
+///
+/// EngineId
+///
+////////////////////////////////////////////////////////////////////////////////
+/// The name and author an engine reports in the UCI handshake
+#[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq, Eq)]
+pub struct EngineId {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+}
+
+/// Printed as `name by author`, leaving out whatever the engine didn't report
+impl Display for EngineId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (&self.name, &self.author) {
+            (Some(name), Some(author)) => write!(f, "{name} by {author}"),
+            (Some(name), None) => write!(f, "{name}"),
+            (None, Some(author)) => write!(f, "unnamed engine by {author}"),
+            (None, None) => write!(f, "unnamed engine"),
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+///
+/// Tests
+///
+////////////////////////////////////////////////////////////////////////////////
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::Score;
+
+    const FEN: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+    /// A snapshot as written before the format was versioned: a bare list of 
+    /// results, with only the fields the first release knew about
+    fn v1_snapshot() -> String {
+        format!(r#"[
+            {{
+                "position": "{FEN}",
+                "depth": 8,
+                "nodes": 123456,
+                "time": 250,
+                "nps": 493824,
+                "score": -35,
+                "branching_factor": 4.3
+            }}
+        ]"#)
+    }
+
+    #[test]
+    fn loads_v1_list_into_current_model() {
+        let snapshot = Snapshot::from_reader(v1_snapshot().as_bytes()).unwrap();
+
+        assert_eq!(snapshot.version, VERSION);
+        assert!(snapshot.meta.engine.is_none());
+        assert!(snapshot.meta.depth.is_empty());
+        assert_eq!(snapshot.results.len(), 1);
+
+        let result = &snapshot.results[0];
+        assert_eq!(result.position, FEN);
+        assert_eq!(result.depth, 8);
+        assert_eq!(result.nodes.0, 123456);
+        assert_eq!(result.time.0, 250);
+        assert_eq!(result.score, Score::Cp(-35));
+        assert!(result.best_move.is_empty());
+        assert!(result.pv.is_empty());
+        assert!(result.id.is_none());
+        assert_eq!(result.ebf.0, result.branching_factor.0);
+    }
+
+    #[test]
+    fn loads_unversioned_object_as_v1() {
+        let json = format!(r#"{{ "meta": {{ "hash": 16 }}, "results": {} }}"#, v1_snapshot());
+        let snapshot = Snapshot::from_reader(json.as_bytes()).unwrap();
+
+        assert_eq!(snapshot.version, VERSION);
+        assert_eq!(snapshot.meta.hash, Some(16));
+        assert_eq!(snapshot.results.len(), 1);
+        assert_eq!(snapshot.results[0].ebf.0, snapshot.results[0].branching_factor.0);
+    }
+
+    #[test]
+    fn round_trips_current_version() {
+        let snapshot = Snapshot::from_reader(v1_snapshot().as_bytes()).unwrap();
+        let json = serde_json::to_string(&snapshot).unwrap();
+        let reloaded = Snapshot::from_reader(json.as_bytes()).unwrap();
+
+        assert!(json.contains(&format!(r#""version":{VERSION}"#)));
+        assert_eq!(reloaded.results[0].nodes.0, snapshot.results[0].nodes.0);
+    }
+
+    #[test]
+    fn rejects_newer_versions() {
+        let json = format!(r#"{{ "version": {}, "results": [] }}"#, VERSION + 1);
+        let err = Snapshot::from_reader(json.as_bytes()).err().unwrap();
+
+        assert!(err.to_string().contains("only reads up to version"));
+    }
+
+    #[test]
+    fn load_reports_where_the_json_is_corrupt() {
+        let path = std::env::temp_dir()
+            .join(format!("chess-bench-corrupt-{}.json", std::process::id()));
+
+        // A trailing comma on the third line
+        std::fs::write(&path, "{\n  \"version\": 2,\n  \"results\": [],\n}\n").unwrap();
+        let err = Snapshot::load(&path).err().unwrap().to_string();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(err.contains(&path.display().to_string()), "{err}");
+        assert!(err.contains("line 4 column 1"), "{err}");
+        assert!(err.contains("--force-suite"), "{err}");
+    }
+}
@@ -0,0 +1,322 @@
+use std::f64::consts::PI;
+use std::fmt::Display;
+use std::ops::{Add, Div};
+
+use clap::ValueEnum;
+
+/// How to summarize a metric across all of the positions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum Aggregate {
+    /// The arithmetic mean
+    #[default]
+    Mean,
+
+    /// The median, which isn't thrown off by a single outlier
+    Median,
+}
+
+/// How to tell the outliers from the rest of the values
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutlierRule {
+    /// Tukey's fences: more than 1.5 times the interquartile range below the
+    /// first quartile, or above the third
+    Iqr,
+
+    /// More than 3 standard deviations away from the mean
+    ZScore,
+}
+
+impl Display for OutlierRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OutlierRule::Iqr => write!(f, "more than 1.5 IQR outside of the quartiles"),
+            OutlierRule::ZScore => write!(f, "more than 3 standard deviations from the mean"),
+        }
+    }
+}
+
+/// Flag which of the values are outliers according to the rule. Nothing is 
+/// flagged when there's no spread to speak of.
+pub fn outliers(values: &[f64], rule: OutlierRule) -> Vec<bool> {
+    let (low, high) = match rule {
+        OutlierRule::Iqr => {
+            let (Some(q1), Some(q3)) = (percentile(values, 25.0), percentile(values, 75.0)) else {
+                return vec![false; values.len()];
+            };
+
+            let iqr = q3 - q1;
+            (q1 - 1.5 * iqr, q3 + 1.5 * iqr)
+        },
+
+        OutlierRule::ZScore => {
+            let Some(stddev) = stddev(values) else {
+                return vec![false; values.len()];
+            };
+
+            let mean = mean(values);
+            (mean - 3.0 * stddev, mean + 3.0 * stddev)
+        },
+    };
+
+    values.iter()
+        .map(|&value| value < low || value > high)
+        .collect()
+}
+
+/// The arithmetic mean of the values, or 0 when there aren't any
+pub fn mean(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+/// The geometric mean of the values, which is the right way to average 
+/// ratios like the branching factor. Values that aren't positive don't have
+/// a logarithm, so they're left out. Returns 0 when there aren't any left.
+pub fn geometric_mean(values: &[f64]) -> f64 {
+    let logs = values.iter()
+        .filter(|value| value.is_finite() && **value > 0.0)
+        .map(|value| value.ln())
+        .collect::<Vec<_>>();
+
+    if logs.is_empty() {
+        return 0.0;
+    }
+
+    mean(&logs).exp()
+}
+
+/// The sample standard deviation of the values. Returns `None` when there are
+/// fewer than two values, since there's no spread to speak of.
+pub fn stddev(values: &[f64]) -> Option<f64> {
+    if values.len() < 2 {
+        return None;
+    }
+
+    let mean = mean(values);
+    let variance = values.iter()
+        .map(|value| (value - mean).powi(2))
+        .sum::<f64>() / (values.len() - 1) as f64;
+
+    Some(variance.sqrt())
+}
+
+/// The median of the values, when ordered by the key. For an even number of
+/// values, this is the average of the middle two. Returns the default value 
+/// when there are no values.
+pub fn median_by<T>(values: impl IntoIterator<Item = T>, key: impl Fn(&T) -> f64) -> T 
+where 
+    T: Default + Add<Output = T> + Div<usize, Output = T>
+{
+    let mut values = values.into_iter().collect::<Vec<_>>();
+    values.sort_by(|a, b| key(a).total_cmp(&key(b)));
+
+    let mid = values.len() / 2;
+
+    if values.is_empty() {
+        T::default()
+    } else if values.len() % 2 == 1 {
+        values.swap_remove(mid)
+    } else {
+        let upper = values.swap_remove(mid);
+        let lower = values.swap_remove(mid - 1);
+        (lower + upper) / 2
+    }
+}
+
+/// The `p`th percentile of the values (with `p` between 0 and 100), 
+/// interpolating linearly between the two closest values. Returns `None` when
+/// there are no values.
+pub fn percentile(values: &[f64], p: f64) -> Option<f64> {
+    let mut values = values.to_vec();
+    values.sort_by(f64::total_cmp);
+
+    let last = values.len().checked_sub(1)?;
+    let rank = p.clamp(0.0, 100.0) / 100.0 * last as f64;
+    let lower = rank.floor() as usize;
+    let upper = rank.ceil() as usize;
+    let weight = rank - lower as f64;
+
+    Some(values[lower] + weight * (values[upper] - values[lower]))
+}
+
+/// The outcome of a paired t-test
+#[derive(Debug, Clone, Copy)]
+pub struct TTest {
+    /// The t statistic. Positive when the second values are higher.
+    pub t: f64,
+
+    /// The two-sided p-value
+    pub p: f64,
+
+    /// The number of pairs
+    pub n: usize,
+}
+
+/// A two-sided paired t-test, checking whether the differences between the 
+/// first and second value of every pair average out to zero. Returns `None`
+/// when there are fewer than two pairs.
+pub fn paired_t_test(pairs: &[(f64, f64)]) -> Option<TTest> {
+    let differences = pairs.iter()
+        .map(|(first, second)| second - first)
+        .collect::<Vec<_>>();
+
+    let n = differences.len();
+    let mean = mean(&differences);
+    let stddev = stddev(&differences)?;
+
+    // Every pair changed by the exact same amount, so there's no doubt about
+    // whether it changed
+    if stddev == 0.0 {
+        return Some(match mean {
+            0.0 => TTest { t: 0.0, p: 1.0, n },
+            _ => TTest { t: mean.signum() * f64::INFINITY, p: 0.0, n },
+        });
+    }
+
+    let t = mean / (stddev / (n as f64).sqrt());
+    let df = (n - 1) as f64;
+    let p = incomplete_beta(df / 2.0, 0.5, df / (df + t * t));
+
+    Some(TTest { t, p, n })
+}
+
+/// The regularized incomplete beta function `I_x(a, b)`, which gives the 
+/// tails of Student's t-distribution
+fn incomplete_beta(a: f64, b: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+
+    if x >= 1.0 {
+        return 1.0;
+    }
+
+    let front = (ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) 
+        + a * x.ln() + b * (1.0 - x).ln()).exp();
+
+    // The continued fraction only converges quickly on one side, so use the
+    // symmetry `I_x(a, b) = 1 - I_(1-x)(b, a)` for the other
+    if x < (a + 1.0) / (a + b + 2.0) {
+        front * beta_fraction(a, b, x) / a
+    } else {
+        1.0 - front * beta_fraction(b, a, 1.0 - x) / b
+    }
+}
+
+/// The continued fraction for the incomplete beta function, evaluated with
+/// the modified Lentz method
+fn beta_fraction(a: f64, b: f64, x: f64) -> f64 {
+    const TINY: f64 = 1e-300;
+    const EPSILON: f64 = 1e-12;
+
+    // Keep the terms from hitting zero
+    let nonzero = |value: f64| if value.abs() < TINY { TINY } else { value };
+
+    let mut c = 1.0;
+    let mut d = 1.0 / nonzero(1.0 - (a + b) * x / (a + 1.0));
+    let mut fraction = d;
+
+    for m in 1..=300 {
+        let m = m as f64;
+
+        let even = m * (b - m) * x / ((a + 2.0 * m - 1.0) * (a + 2.0 * m));
+        d = 1.0 / nonzero(1.0 + even * d);
+        c = nonzero(1.0 + even / c);
+        fraction *= d * c;
+
+        let odd = -(a + m) * (a + b + m) * x / ((a + 2.0 * m) * (a + 2.0 * m + 1.0));
+        d = 1.0 / nonzero(1.0 + odd * d);
+        c = nonzero(1.0 + odd / c);
+        fraction *= d * c;
+
+        if (d * c - 1.0).abs() < EPSILON {
+            break;
+        }
+    }
+
+    fraction
+}
+
+/// The natural logarithm of the gamma function, using the Lanczos 
+/// approximation
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+
+    // Reflection formula, for the left half-plane
+    if x < 0.5 {
+        return (PI / (PI * x).sin()).ln() - ln_gamma(1.0 - x);
+    }
+
+    let x = x - 1.0;
+    let t = x + 7.5;
+
+    let sum = COEFFICIENTS[1..].iter()
+        .enumerate()
+        .fold(COEFFICIENTS[0], |sum, (i, coefficient)| sum + coefficient / (x + i as f64 + 1.0));
+
+    0.5 * (2.0 * PI).ln() + (x + 0.5) * t.ln() - t + sum.ln()
+}
+
+////////////////////////////////////////////////////////////////////////////////
+///
+/// Tests
+///
+////////////////////////////////////////////////////////////////////////////////
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(value: f64, expected: f64) {
+        assert!((value - expected).abs() < 1e-9, "{value} != {expected}");
+    }
+
+    #[test]
+    fn stddev_of_known_dataset() {
+        // The squared deviations from the mean of 5 add up to 32
+        let values = [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0];
+
+        assert_close(stddev(&values).unwrap(), (32.0f64 / 7.0).sqrt());
+        assert_eq!(stddev(&[5.0]), None);
+        assert_eq!(stddev(&[]), None);
+    }
+
+    #[test]
+    fn geometric_mean_of_ratios() {
+        // Doubling and halving cancel out, which the arithmetic mean misses
+        let values = [2.0, 0.5];
+
+        assert_close(geometric_mean(&values), 1.0);
+        assert_close(mean(&values), 1.25);
+
+        assert_close(geometric_mean(&[1.0, 10.0, 100.0]), 10.0);
+        assert_close(geometric_mean(&[0.0, 4.0]), 4.0);
+        assert_close(geometric_mean(&[]), 0.0);
+    }
+
+    #[test]
+    fn percentiles_of_known_distribution() {
+        // 1 through 100, out of order, so the percentiles interpolate between ranks
+        let mut values = (1..=100).map(f64::from).collect::<Vec<_>>();
+        values.reverse();
+
+        assert_close(percentile(&values, 50.0).unwrap(), 50.5);
+        assert_close(percentile(&values, 90.0).unwrap(), 90.1);
+        assert_close(percentile(&values, 99.0).unwrap(), 99.01);
+        assert_close(percentile(&values, 100.0).unwrap(), 100.0);
+        assert_close(percentile(&[7.0], 90.0).unwrap(), 7.0);
+        assert_eq!(percentile(&[], 50.0), None);
+    }
+}
@@ -0,0 +1,98 @@
+use std::str::FromStr;
+use anyhow::anyhow;
+
+use crate::engine::EngineOption;
+
+/// The most combinations of options a sweep runs, since every one of them 
+/// runs the entire suite
+pub const MAX_COMBINATIONS: usize = 100;
+
+/// A UCI option, along with the values it should be swept over.
+///
+/// Parsed from a string of the form `name=start..end:step`, e.g.,
+/// `Contempt=-50..50:25`, or from a list of values, e.g., `Hash=16,64,256`.
+/// The step is optional and defaults to 1. The range is inclusive on both 
+/// ends.
+#[derive(Debug, Clone)]
+pub struct OptionSweep {
+    pub name: String,
+    pub values: Vec<String>,
+}
+
+impl FromStr for OptionSweep {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let (name, range) = s.split_once('=')
+            .ok_or_else(|| anyhow!("Invalid sweep '{s}', expected name=start..end:step or name=a,b,c"))?;
+
+        let name = name.trim();
+
+        if name.is_empty() {
+            return Err(anyhow!("Invalid sweep '{s}', missing option name"));
+        }
+
+        if !range.contains("..") {
+            let values = range.split(',')
+                .map(|value| value.trim().to_string())
+                .collect::<Vec<_>>();
+
+            if values.iter().any(String::is_empty) {
+                return Err(anyhow!("Invalid sweep '{s}', empty value in the list"));
+            }
+
+            return Ok(Self { name: name.to_string(), values });
+        }
+
+        let (range, step) = match range.rsplit_once(':') {
+            Some((range, step)) => (range, step.trim().parse::<i64>()?),
+            None => (range, 1),
+        };
+
+        if step <= 0 {
+            return Err(anyhow!("Invalid sweep '{s}', step must be positive"));
+        }
+
+        let (start, end) = range.split_once("..")
+            .ok_or_else(|| anyhow!("Invalid sweep '{s}', expected a range start..end"))?;
+
+        let start: i64 = start.trim().parse()?;
+        let end: i64 = end.trim().parse()?;
+
+        if start > end {
+            return Err(anyhow!("Invalid sweep '{s}', start is larger than end"));
+        }
+
+        let values = (start..=end)
+            .step_by(step as usize)
+            .map(|value| value.to_string())
+            .collect();
+
+        Ok(Self { name: name.to_string(), values })
+    }
+}
+
+/// Every combination of the values of the sweeps, as the options to set for
+/// each of them, with the first sweep varying the slowest. Only the first 
+/// `limit` combinations are generated.
+pub fn combinations(sweeps: &[OptionSweep], limit: usize) -> Vec<Vec<EngineOption>> {
+    sweeps.iter().fold(vec![Vec::new()], |combinations, sweep| {
+        combinations.iter()
+            .flat_map(|combination| sweep.values.iter().map(|value| {
+                let mut combination = combination.clone();
+                combination.push(EngineOption { 
+                    name: sweep.name.clone(), 
+                    value: Some(value.clone()) 
+                });
+
+                combination
+            }))
+            .take(limit)
+            .collect()
+    })
+}
+
+/// The number of combinations of the values of the sweeps
+pub fn combination_count(sweeps: &[OptionSweep]) -> usize {
+    sweeps.iter().fold(1, |count, sweep| count.saturating_mul(sweep.values.len()))
+}
@@ -1,10 +1,56 @@
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
 const SEP_WIDTH: usize = 3;
 
+/// How the contents of a column are aligned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Alignment {
+    Left,
+    Right,
+    Center,
+}
+
+impl Alignment {
+    /// Pad the value up to the width, according to the alignment. The width
+    /// is measured in columns on screen, rather than bytes or chars, so color
+    /// codes and wide characters don't throw off the padding.
+    fn pad(self, value: &str, width: usize) -> String {
+        let padding = width.saturating_sub(visual_width(value));
+
+        let (left, right) = match self {
+            Alignment::Left => (0, padding),
+            Alignment::Right => (padding, 0),
+            Alignment::Center => (padding / 2, padding - padding / 2),
+        };
+
+        format!("{}{value}{}", " ".repeat(left), " ".repeat(right))
+    }
+}
+
+/// A line of a table that's buffered until it gets rendered
+#[derive(Clone)]
+enum Line {
+    Row(Vec<String>),
+    Separator,
+}
+
 /// Helper struct that lets up print tabulated data in a sane way
+#[derive(Clone)]
 pub struct Tabulator {
     cols: usize,
     widths: Vec<usize>,
     names: Vec<String>,
+    alignments: Vec<Alignment>,
+    heading_alignments: Vec<Alignment>,
+
+    /// Whether to cut off values that don't fit their column
+    truncate: Vec<bool>,
+
+    /// Whether to size the columns to fit their contents when rendering
+    auto: bool,
+
+    /// The lines buffered for `render`
+    lines: Vec<Line>,
 }
 
 /// Creation/builder methods
@@ -14,13 +60,86 @@ impl Tabulator {
             cols: 0,
             widths: Vec::new(),
             names: Vec::new(),
+            alignments: Vec::new(),
+            heading_alignments: Vec::new(),
+            truncate: Vec::new(),
+            auto: false,
+            lines: Vec::new(),
         }
     }
 
-    pub fn add_col(&mut self, heading: &'static str, width: usize) {
+    /// Create a table that sizes its columns to fit their contents. The 
+    /// widths passed to `add_col` are treated as minimum widths.
+    ///
+    /// Rows need to be buffered with `push_row`, since the widths are only 
+    /// known once all of them are in. Print the table with `render`.
+    pub fn auto() -> Self {
+        Self { auto: true, ..Self::new() }
+    }
+
+    /// Add a column. By default, the first column is left-aligned, the others
+    /// are right-aligned, and the headings are centered.
+    pub fn add_col(&mut self, heading: &str, width: usize) -> &mut Self {
+        let alignment = if self.cols == 0 { Alignment::Left } else { Alignment::Right };
+
         self.names.push(heading.to_string());
         self.widths.push(width);
+        self.alignments.push(alignment);
+        self.heading_alignments.push(Alignment::Center);
+        self.truncate.push(false);
         self.cols += 1;
+
+        self
+    }
+
+    /// Set the alignment of the column that was added last, for both the 
+    /// heading and the values
+    pub fn align(&mut self, alignment: Alignment) -> &mut Self {
+        if let Some(last) = self.alignments.last_mut() {
+            *last = alignment;
+        }
+
+        if let Some(last) = self.heading_alignments.last_mut() {
+            *last = alignment;
+        }
+
+        self
+    }
+
+    /// Cut off any values in the column that was added last that don't fit
+    /// the column, ending them in an ellipsis. Values overflow the column by
+    /// default.
+    pub fn truncate(&mut self) -> &mut Self {
+        if let Some(last) = self.truncate.last_mut() {
+            *last = true;
+        }
+
+        self
+    }
+
+    /// The number of columns in the table
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    /// The column headings
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// The alignment of the values in every column
+    pub fn alignments(&self) -> &[Alignment] {
+        &self.alignments
+    }
+
+    /// Buffer a row, to be printed by `render`
+    pub fn push_row(&mut self, values: &[String]) {
+        self.lines.push(Line::Row(values.to_vec()));
+    }
+
+    /// Buffer a row separator, to be printed by `render`
+    pub fn push_separator(&mut self) {
+        self.lines.push(Line::Separator);
     }
 }
 
@@ -31,16 +150,16 @@ impl Tabulator {
         let mut row = String::new();
 
         // Top line
-        row.push_str(&format!("┌"));
+        row.push('┌');
         for (i, &width) in self.widths.iter().enumerate() {
-            row.push_str(&format!("{}", "─".repeat(width + SEP_WIDTH/2 + 1)));
+            row.push_str(&"─".repeat(width + SEP_WIDTH/2 + 1));
 
             if i < self.cols - 1 {
-                row.push_str(&format!("┬"));
+                row.push('┬');
             }
         }
-        row.push_str(&format!("┐"));
-        row.push_str(&format!("\n"));
+        row.push('┐');
+        row.push('\n');
 
         // Heading names
         row.push_str(&format!("{:<1$}", "│", SEP_WIDTH/2 + 1));
@@ -50,13 +169,13 @@ impl Tabulator {
                 row.push_str(&sep);
             }
 
-            let cell = format!("{:^1$}", name, width);
+            let cell = self.heading_alignments[i].pad(name, *width);
 
             row.push_str(&cell);
         }
 
         row.push_str(&format!("{:>1$}", "│", SEP_WIDTH/2 + 1));
-        row.push_str(&format!("\n"));
+        row.push('\n');
 
         // Bottom line
         row.push_str(&self.row_separator());
@@ -69,16 +188,16 @@ impl Tabulator {
         let mut row = String::new();
 
         // Top line
-        row.push_str(&format!("└"));
+        row.push('└');
         for (i, &width) in self.widths.iter().enumerate() {
-            row.push_str(&format!("{}", "─".repeat(width + SEP_WIDTH/2 + 1)));
+            row.push_str(&"─".repeat(width + SEP_WIDTH/2 + 1));
 
             if i < self.cols - 1 {
-                row.push_str(&format!("┴"));
+                row.push('┴');
             }
         }
-        row.push_str(&format!("┘"));
-        row.push_str(&format!("\n"));
+        row.push('┘');
+        row.push('\n');
 
         row
 
@@ -89,21 +208,15 @@ impl Tabulator {
         let mut row = format!("{:<1$}", "│", SEP_WIDTH/2 + 1);
 
         for (i, (value, width)) in values.iter().zip(self.widths.iter()).enumerate() {
-            // Gotta figure out the "visual" length (ignoring color codes) so 
-            // we can padd the cell correctly
-            let stripped = strip_ansi_escapes::strip_str(value);
-            let delta = value.len() - stripped.len();
-
             if i > 0 {
                 let sep = format!("{:^1$}", "│", SEP_WIDTH);
                 row.push_str(&sep);
             }
 
-            // TODO: Make the alignment configurable
-            let cell = if i == 0 {
-                format!("{:<1$}", value, width + delta)
+            let cell = if self.truncate[i] {
+                self.alignments[i].pad(&truncate(value, *width), *width)
             } else {
-                format!("{:>1$}", value, width + delta)
+                self.alignments[i].pad(value, *width)
             };
 
             row.push_str(&cell);
@@ -114,18 +227,182 @@ impl Tabulator {
         row
     }
 
+    /// Return the entire table, with all of the buffered lines, as a string.
+    /// For auto-sized tables, every column is made wide enough to fit its
+    /// heading and all of its values.
+    pub fn render(&self) -> String {
+        let mut table = self.clone();
+
+        if self.auto {
+            for (i, width) in table.widths.iter_mut().enumerate() {
+                let values = self.lines.iter().filter_map(|line| match line {
+                    Line::Row(values) => values.get(i),
+                    Line::Separator => None,
+                });
+
+                *width = values
+                    .chain(std::iter::once(&self.names[i]))
+                    .map(|value| visual_width(value))
+                    .fold(*width, usize::max);
+            }
+        }
+
+        let mut output = table.header();
+        output.push('\n');
+
+        for line in &self.lines {
+            match line {
+                Line::Row(values) => output.push_str(&table.row(values)),
+                Line::Separator => output.push_str(&table.row_separator()),
+            }
+
+            output.push('\n');
+        }
+
+        output.push_str(&table.footer());
+
+        output
+    }
+
     pub fn row_separator(&self) -> String {
         let mut row = String::new();
-        row.push_str(&format!("├"));
+        row.push('├');
         for (i, &width) in self.widths.iter().enumerate() {
-            row.push_str(&format!("{}", "─".repeat(width + SEP_WIDTH/2 + 1)));
+            row.push_str(&"─".repeat(width + SEP_WIDTH/2 + 1));
 
             if i < self.cols - 1 {
-                row.push_str(&format!("┼"));
+                row.push('┼');
             }
         }
-        row.push_str(&format!("┤"));
+        row.push('┤');
 
         row
     }
 }
+
+/// The number of columns a value takes up on screen, ignoring color codes.
+/// Accented characters take up a single column, and full-width (e.g., CJK)
+/// characters take up two.
+fn visual_width(value: &str) -> usize {
+    strip_ansi_escapes::strip_str(value).width()
+}
+
+/// Cut the value down to the given on-screen width, ending it in an ellipsis
+/// when it doesn't fit. Color codes are kept, and don't count towards the 
+/// width.
+fn truncate(value: &str, width: usize) -> String {
+    if visual_width(value) <= width {
+        return value.to_string();
+    }
+
+    // Leave room for the ellipsis
+    let budget = width.saturating_sub(1);
+    let mut truncated = String::new();
+    let mut used = 0;
+    let mut colored = false;
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        // Copy escape sequences over whole, up to the terminating letter
+        if c == '\x1b' {
+            colored = true;
+            truncated.push(c);
+
+            for c in chars.by_ref() {
+                truncated.push(c);
+
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+
+            continue;
+        }
+
+        let char_width = c.width().unwrap_or(0);
+
+        if used + char_width > budget {
+            break;
+        }
+
+        truncated.push(c);
+        used += char_width;
+    }
+
+    truncated.push('…');
+
+    // The reset at the end of the value may have been cut off
+    if colored {
+        truncated.push_str("\x1b[0m");
+    }
+
+    truncated
+}
+
+////////////////////////////////////////////////////////////////////////////////
+///
+/// Tests
+///
+////////////////////////////////////////////////////////////////////////////////
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The on-screen width of every line of the rendered table
+    fn line_widths(table: &str) -> Vec<usize> {
+        table.lines().map(visual_width).collect()
+    }
+
+    #[test]
+    fn auto_table_fits_every_cell() {
+        let mut table = Tabulator::auto();
+        table.add_col("FEN", 3).truncate();
+        table.add_col("Nodes", 1);
+
+        let rows = [
+            ["8/8/8/4k3/8/8/8/4K3 w - - 0 1", "123456789"],
+            ["rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", "20"],
+        ];
+
+        for row in rows {
+            table.push_row(&row.map(String::from));
+        }
+
+        let rendered = table.render();
+
+        for value in rows.iter().flatten() {
+            assert!(rendered.contains(value), "{value} got clipped:\n{rendered}");
+        }
+
+        let widths = line_widths(&rendered);
+        assert!(widths.iter().all(|&width| width == widths[0]), "{rendered}");
+    }
+
+    #[test]
+    fn wide_and_accented_labels_keep_borders_aligned() {
+        assert_eq!(visual_width("Réti"), 4);
+        assert_eq!(visual_width("定式"), 4);
+        assert_eq!(visual_width("\x1b[31mRéti\x1b[0m"), 4);
+
+        assert_eq!(Alignment::Left.pad("Réti", 6), "Réti  ");
+        assert_eq!(Alignment::Right.pad("定式", 6), "  定式");
+        assert_eq!(Alignment::Center.pad("定式", 7), " 定式  ");
+
+        let mut table = Tabulator::new();
+        table.add_col("Label", 12).align(Alignment::Left);
+        table.add_col("Nodes", 8);
+
+        let mut rendered = table.header();
+        rendered.push('\n');
+
+        for label in ["Réti", "Nimzo-Indién", "定式", "\x1b[32mCaro–Kann\x1b[0m"] {
+            rendered.push_str(&table.row(&[label.to_string(), String::from("1234")]));
+            rendered.push('\n');
+        }
+
+        rendered.push_str(&table.footer());
+
+        let widths = line_widths(&rendered);
+        assert!(widths.iter().all(|&width| width == widths[0]), "{rendered}");
+    }
+}
@@ -0,0 +1,17 @@
+#!/bin/sh
+# A fake engine that sends an info line that can't be parsed in the middle of
+# its search output
+
+while read -r line; do
+    case "$line" in
+        uci) echo "id name garbled"; echo "uciok" ;;
+        isready) echo "readyok" ;;
+        go*)
+            echo "info depth 1 nodes 20 time 1 score cp 10 pv e2e4"
+            echo "info depth two nodes lots"
+            echo "info depth 2 nodes 400 time 2 score cp 15 pv d2d4"
+            echo "bestmove d2d4"
+            ;;
+        quit) exit 0 ;;
+    esac
+done
@@ -0,0 +1,19 @@
+#!/bin/sh
+# A fake engine that searches two lines, and sends a secondary line update
+# without a score after the last full iteration
+
+while read -r line; do
+    case "$line" in
+        uci) echo "id name multipv"; echo "uciok" ;;
+        isready) echo "readyok" ;;
+        go*)
+            echo "info depth 1 multipv 1 nodes 20 time 1 score cp 30 pv e2e4"
+            echo "info depth 1 multipv 2 nodes 20 time 1 score cp 20 pv d2d4"
+            echo "info depth 2 multipv 1 nodes 400 time 2 score cp 25 pv e2e4 e7e5"
+            echo "info depth 2 multipv 2 nodes 400 time 2 score cp 15 pv d2d4 d7d5"
+            echo "info depth 3 multipv 2 currmove g1f3 currmovenumber 2"
+            echo "bestmove e2e4"
+            ;;
+        quit) exit 0 ;;
+    esac
+done
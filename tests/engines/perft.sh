@@ -0,0 +1,24 @@
+#!/bin/sh
+# A fake engine that knows the perft counts of the start position, and
+# answers 0 for any other position
+
+startpos="position fen rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+
+while read -r line; do
+    case "$line" in
+        uci) echo "id name perft"; echo "uciok" ;;
+        isready) echo "readyok" ;;
+        "$startpos") position=startpos ;;
+        position*) position=other ;;
+        "go perft "*)
+            case "$position ${line#go perft }" in
+                "startpos 1") echo "Nodes searched: 20" ;;
+                "startpos 2") echo "Nodes searched: 400" ;;
+                "startpos 3") echo "Nodes searched: 8902" ;;
+                "startpos 4") echo "Nodes searched: 197281" ;;
+                *) echo "Nodes searched: 0" ;;
+            esac
+            ;;
+        quit) exit 0 ;;
+    esac
+done
@@ -0,0 +1,31 @@
+#!/bin/sh
+# A fake engine that answers right away, except in Kiwipete, where it keeps
+# searching until it's told to stop. With `--ignore-stop`, it doesn't stop at
+# all.
+
+kiwipete="position fen r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1"
+
+while read -r line; do
+    case "$line" in
+        uci) echo "id name slow position"; echo "uciok" ;;
+        isready) echo "readyok" ;;
+        "$kiwipete") slow=1 ;;
+        position*) slow=0 ;;
+        go*)
+            echo "info depth 1 nodes 20 time 1 score cp 10 pv e2e4"
+
+            if [ "$slow" = 1 ]; then
+                searching=1
+            else
+                echo "bestmove e2e4"
+            fi
+            ;;
+        stop)
+            if [ "$searching" = 1 ] && [ "$1" != "--ignore-stop" ]; then
+                searching=0
+                echo "bestmove a2a3"
+            fi
+            ;;
+        quit) exit 0 ;;
+    esac
+done
@@ -0,0 +1,15 @@
+#!/bin/sh
+# A fake engine that takes its time to answer `isready`, like an engine that
+# loads a large network on startup
+
+while read -r line; do
+    case "$line" in
+        uci) echo "id name slow readyok"; echo "uciok" ;;
+        isready) sleep 0.3; echo "readyok" ;;
+        go*)
+            echo "info depth 1 nodes 20 time 1 score cp 10 pv e2e4"
+            echo "bestmove e2e4"
+            ;;
+        quit) exit 0 ;;
+    esac
+done
@@ -0,0 +1,11 @@
+#!/bin/sh
+# A fake engine that completes the handshake, but never finishes a search. It
+# ignores `stop` and `quit` too, so it has to be killed.
+
+while read -r line; do
+    case "$line" in
+        uci) echo "id name stall"; echo "uciok" ;;
+        isready) echo "readyok" ;;
+        go*) echo "info depth 1 nodes 20 time 1 score cp 10 pv e2e4" ;;
+    esac
+done